@@ -1,121 +1,613 @@
-use proc_macro::TokenStream;
-use proc_macro2::Span;
-use quote::quote;
+use quote::{format_ident, quote};
 use syn::{
-    Ident, Type, TypeFnPtr, TypeImplTrait, TypeParamBound, TypePath, TypePtr, TypeReference,
-    TypeTraitObject, TypeTuple, punctuated::Punctuated, token::Plus,
+    GenericArgument, Generics, Ident, ImplItemFn, Path, PathArguments, TraitBound, Type, TypeFnPtr,
+    TypeImplTrait, TypeParamBound, TypePath, TypePtr, TypeReference, TypeTraitObject, TypeTuple,
+    parse_quote, punctuated::Punctuated, token::Plus,
 };
 
-use crate::extract::MockPrepared;
+use crate::extract::{MockPrepared, mockable_bounds};
+use crate::options::Naming;
+use crate::pimpl::MockContext;
+use crate::record::{Tracking, generate_mock_state};
 
+/// Finds the mock that replaces the type of a field. `name` is used to name the mocks that are
+/// generated for the field, such as the ones for function pointers.
 #[allow(clippy::match_same_arms)]
-pub fn get_mocking_candidate(field: &Type) -> MockPrepared {
+pub fn get_mocking_candidate(field: &Type, name: &Ident, naming: &Naming) -> MockPrepared {
     match field {
-        Type::Array(arr) => get_mocking_candidate(&arr.elem),
-        Type::FnPtr(f) => mock_function(f),
-        Type::Group(g) => get_mocking_candidate(&g.elem),
-        Type::ImplTrait(imp) => mock_and_impl_trait_for_it(imp),
-        Type::Infer(_) => unreachable!(),
-        //TODO:: consider if we need to consider this case or not
-        Type::Macro(_) => todo!(),
-        Type::Never(_) => todo!(),
-        Type::Paren(p) => get_mocking_candidate(&p.elem),
+        Type::Array(arr) => {
+            let mut array = arr.clone();
+            let prepared = get_mocking_candidate(&arr.elem, name, naming);
+            *array.elem = prepared.mocked_type;
+            MockPrepared::new(Type::Array(array), prepared.stream)
+        }
+        Type::FnPtr(f) => mock_function(f, name, naming),
+        Type::Group(g) => get_mocking_candidate(&g.elem, name, naming),
+        Type::ImplTrait(imp) => mock_and_impl_trait_for_it(imp, name, naming),
+        Type::Infer(_) => MockPrepared::unsupported(field, "the type of the field must be given"),
+        Type::Macro(_) => MockPrepared::unsupported(
+            field,
+            "types produced by macros cannot be mocked, use the type the macro expands to",
+        ),
+        Type::Never(_) => MockPrepared::unsupported(field, "`!` cannot be mocked"),
+        Type::Paren(p) => get_mocking_candidate(&p.elem, name, naming),
         Type::Path(
             p @ TypePath {
-                qself: Some(q),
+                qself: Some(_),
                 path: _,
                 attrs: _,
             },
-        ) => mock_associated_type(p),
-        Type::Path(p) => resolve_path_and_mock(p),
-        Type::Ptr(p) => mock_pointer(p),
-        Type::Reference(r) => mock_reference(r),
-        Type::Slice(sl) => get_mocking_candidate(&sl.elem),
-        Type::TraitObject(dym) => mock_and_impl_trait_for_it(dym),
-        Type::Tuple(tup) => mock_tuple(tup),
-        Type::Verbatim(ts) => todo!(),
-        _ => unreachable!(),
+        ) => mock_associated_type(p, name, naming),
+        Type::Path(p) => mock_generic_arguments(p, name, naming)
+            .unwrap_or_else(|| resolve_path_and_mock(p, naming)),
+        Type::Ptr(p) => mock_pointer(p, name, naming),
+        Type::Reference(r) => mock_reference(r, name, naming),
+        Type::Slice(sl) => {
+            let mut slice = sl.clone();
+            let prepared = get_mocking_candidate(&sl.elem, name, naming);
+            *slice.elem = prepared.mocked_type;
+            MockPrepared::new(Type::Slice(slice), prepared.stream)
+        }
+        Type::TraitObject(dym) => mock_and_impl_trait_for_it(dym, name, naming),
+        Type::Tuple(tup) => mock_tuple(tup, name, None, naming),
+        _ => MockPrepared::unsupported(field, "this type cannot be mocked"),
     }
 }
 
-pub fn mock_function(f: &TypeFnPtr) -> MockPrepared {
-    todo!()
-}
+/// Replaces a function pointer with a `<Name>Mock` type that dereferences to a function pointer of
+/// the same signature. Calls made through it are recorded and return values configured in the
+/// same way as for the methods of a `#[mock_impl]`, through the `expect_call`, `returning_call`
+/// and `return_const_call` functions of the mock.
+pub fn mock_function(f: &TypeFnPtr, name: &Ident, naming: &Naming) -> MockPrepared {
+    let mock_name = naming.mock_name(name);
+    let context = match MockContext::new(&parse_quote!(#name), naming) {
+        Ok(context) => context,
+        Err(error) => {
+            return MockPrepared::new(Type::FnPtr(f.clone()), Some(error.to_compile_error()));
+        }
+    };
 
-pub fn mock_and_impl_trait_for_it<T: Traitified>(imp: &T) -> MockPrepared {
-    todo!()
-}
+    let lifetimes = f.lifetimes.as_ref().map(|bound| &bound.lifetimes);
+    let inputs = f.inputs.iter().enumerate().map(|(i, arg)| {
+        let ident = arg
+            .name
+            .as_ref()
+            .map_or_else(|| format_ident!("arg{i}"), |(ident, _)| ident.clone());
+        let ty = &arg.ty;
+        quote!(#ident: #ty)
+    });
+    let output = &f.output;
+    let function: ImplItemFn = parse_quote! {
+        pub fn call<#lifetimes>(#(#inputs),*) #output {}
+    };
+    let (function, helpers) = context.replace_self_from_function_with_mocks(
+        function,
+        &Generics::default(),
+        None,
+        None,
+        &[String::from("call")],
+    );
+    let vis = naming.visibility();
+    let state = generate_mock_state(&vis, &mock_name, &Generics::default(), &Tracking::Shared);
 
-pub fn create_mock(mock_name: &str) -> TokenStream {
     let stream = quote! {
-        struct mock_name {}
+        #[derive(Clone, Copy, Debug)]
+        #vis struct #mock_name {
+            function: #f,
+        }
+
+        impl #mock_name {
+            pub fn mock_new() -> Self {
+                Self {
+                    function: Self::call,
+                }
+            }
+
+            #function
+
+            #helpers
+        }
+
+        impl ::std::default::Default for #mock_name {
+            fn default() -> Self {
+                Self::mock_new()
+            }
+        }
+
+        impl ::std::ops::Deref for #mock_name {
+            type Target = #f;
+
+            fn deref(&self) -> &Self::Target {
+                &self.function
+            }
+        }
+
+        #state
     };
 
-    TokenStream::from(stream)
+    MockPrepared::new(parse_quote!(#mock_name), Some(stream))
 }
 
-pub fn implement_trait_for_mock(tr: &Ident) -> TokenStream {
-    todo!()
+/// Replaces a trait object or `impl Trait` with the mock of its trait. A single trait is replaced
+/// with the mock generated by `#[mock]` on it, while a combination of traits such as
+/// `impl Read + Write` is reported, as only the names of the traits are known here and a mock
+/// implementing all of them would need their methods.
+pub fn mock_and_impl_trait_for_it<T: Traitified>(
+    imp: &T,
+    name: &Ident,
+    naming: &Naming,
+) -> MockPrepared {
+    let mut bounds = mockable_bounds(imp);
+    let mocked_type = match (bounds.next(), bounds.next()) {
+        (None, _) => return MockPrepared::new(imp.as_type(), None),
+        (Some(bound), None)
+            if let Some(segment) = bound.segments.last()
+                && let PathArguments::Parenthesized(arguments) = &segment.arguments =>
+        {
+            let inputs = &arguments.inputs;
+            let output = &arguments.output;
+            return mock_function(&parse_quote!(fn(#inputs) #output), name, naming);
+        }
+        (Some(bound), None) => {
+            let mut path = bound.clone();
+            strip_associated_constraints(&mut path);
+            resolve_path_and_mock(
+                &TypePath {
+                    attrs: vec![],
+                    qself: None,
+                    path,
+                },
+                naming,
+            )
+            .mocked_type
+        }
+        (Some(_), Some(_)) => {
+            return MockPrepared::unsupported(
+                &imp.as_type(),
+                "a combination of traits cannot be mocked, use `#[mocked(with = Type)]` with a \
+                 type implementing all of them",
+            );
+        }
+    };
+
+    let stream = implement_trait_for_mock(&mocked_type, imp.bounds());
+    MockPrepared::new(mocked_type, Some(stream))
 }
 
-pub fn mock_pointer(p: &TypePtr) -> MockPrepared {
-    todo!()
+/// Removes constraints such as `Item = u8` from the arguments of a trait, as they do not apply to
+/// the mock type that replaces it.
+fn strip_associated_constraints(path: &mut Path) {
+    let Some(segment) = path.segments.last_mut() else {
+        return;
+    };
+    if let PathArguments::AngleBracketed(arguments) = &mut segment.arguments {
+        arguments.args = arguments
+            .args
+            .iter()
+            .filter(|argument| {
+                matches!(
+                    argument,
+                    GenericArgument::Lifetime(_)
+                        | GenericArgument::Type(_)
+                        | GenericArgument::Const(_)
+                )
+            })
+            .cloned()
+            .collect();
+        if arguments.args.is_empty() {
+            segment.arguments = PathArguments::None;
+        }
+    }
 }
 
-pub fn mock_associated_type(p: &TypePath) -> MockPrepared {
-    todo!()
+/// Checks at compile time that the mock implements every trait of the bounds it replaces,
+/// including auto traits such as `Send` and `Sync`. The bounds with generic arguments are left
+/// out as they may refer to the generics of the type being mocked.
+pub fn implement_trait_for_mock(
+    mock: &Type,
+    bounds: &Punctuated<TypeParamBound, Plus>,
+) -> proc_macro2::TokenStream {
+    let traits: Vec<&TraitBound> = bounds
+        .iter()
+        .filter_map(|bound| match bound {
+            TypeParamBound::Trait(tr) if tr.maybe.is_none() => Some(tr),
+            _ => None,
+        })
+        .filter(|tr| {
+            tr.path
+                .segments
+                .iter()
+                .all(|segment| segment.arguments.is_none())
+        })
+        .collect();
+    if traits.is_empty() {
+        return proc_macro2::TokenStream::new();
+    }
+
+    quote! {
+        const _: () = {
+            fn implements_bounds<T: ?Sized #(+ #traits)*>() {}
+            let _ = implements_bounds::<#mock>;
+        };
+    }
 }
 
-pub fn mock_reference(r: &TypeReference) -> MockPrepared {
-    todo!()
+/// Mocks the type behind a raw pointer, keeping whether it is `*const` or `*mut`.
+pub fn mock_pointer(p: &TypePtr, name: &Ident, naming: &Naming) -> MockPrepared {
+    let mut pointer = p.clone();
+    let prepared = get_mocking_candidate(&p.elem, name, naming);
+    *pointer.elem = prepared.mocked_type;
+    MockPrepared::new(Type::Ptr(pointer), prepared.stream)
 }
 
-pub fn resolve_path_and_mock(path: &TypePath) -> MockPrepared {
+/// Resolves an associated type through the mock of its trait, so that `<B as Backend>::Conn`
+/// becomes `<BackendMock as Backend>::Conn` and takes the type set on the mock of the trait.
+/// Without a trait, as in `<Db>::Conn`, the type itself is mocked.
+pub fn mock_associated_type(p: &TypePath, name: &Ident, naming: &Naming) -> MockPrepared {
+    let Some(qself) = &p.qself else {
+        return resolve_path_and_mock(p, naming);
+    };
+
+    let prepared = if qself.position == 0 {
+        get_mocking_candidate(&qself.ty, name, naming)
+    } else {
+        let mut trait_path = Path {
+            leading_colon: p.path.leading_colon,
+            segments: p
+                .path
+                .segments
+                .iter()
+                .take(qself.position)
+                .cloned()
+                .collect(),
+        };
+        strip_associated_constraints(&mut trait_path);
+        resolve_path_and_mock(
+            &TypePath {
+                attrs: vec![],
+                qself: None,
+                path: trait_path,
+            },
+            naming,
+        )
+    };
+    let mut path = p.clone();
+    if let Some(qself) = &mut path.qself {
+        *qself.ty = prepared.mocked_type;
+    }
+
+    MockPrepared::new(Type::Path(path), prepared.stream)
+}
+
+/// Mocks the type behind a reference, keeping its lifetime and mutability so that `&'a mut Db`
+/// becomes `&'a mut DbMock`.
+pub fn mock_reference(r: &TypeReference, name: &Ident, naming: &Naming) -> MockPrepared {
+    let mut reference = r.clone();
+    let prepared = get_mocking_candidate(&r.elem, name, naming);
+    *reference.elem = prepared.mocked_type;
+    MockPrepared::new(Type::Reference(reference), prepared.stream)
+}
+
+pub fn resolve_path_and_mock(path: &TypePath, naming: &Naming) -> MockPrepared {
     let mut path = path.clone();
-    let segment = path.path.segments.last_mut().unwrap();
-    segment.ident = Ident::new(format!("{}Mock", segment.ident).as_str(), Span::call_site());
+    if naming.is_in_scope()
+        && let Some(last) = path.path.segments.pop()
+    {
+        path.path = last.into();
+    }
+    let Some(segment) = path.path.segments.last_mut() else {
+        return MockPrepared::unsupported(&Type::Path(path), "expected the path of a type");
+    };
+    segment.ident = naming.mock_name(&segment.ident);
     MockPrepared::new(Type::Path(path), None)
 }
 
-pub fn mock_tuple(t: &TypeTuple) -> MockPrepared {
-    todo!()
+/// Containers from the standard library whose type arguments are mocked instead of the container
+/// itself.
+const CONTAINERS: &[&str] = &[
+    "Arc",
+    "BTreeSet",
+    "BinaryHeap",
+    "Box",
+    "Cell",
+    "Cow",
+    "HashSet",
+    "LinkedList",
+    "ManuallyDrop",
+    "Mutex",
+    "OnceCell",
+    "OnceLock",
+    "Option",
+    "Pin",
+    "Rc",
+    "RefCell",
+    "Reverse",
+    "RwLock",
+    "UnsafeCell",
+    "Vec",
+    "VecDeque",
+    "Weak",
+];
+
+/// Maps from the standard library, of which only the values are mocked.
+const MAPS: &[&str] = &["BTreeMap", "HashMap"];
+
+/// Results, of which only the value is mocked and the error kept.
+const RESULTS: &[&str] = &["Result"];
+
+/// Mocks the generic arguments of a type while keeping the type itself. The arguments of the
+/// containers of the standard library are mocked, so that `Vec<Worker>` becomes `Vec<WorkerMock>`
+/// and `HashMap<u32, Worker>` becomes `HashMap<u32, WorkerMock>`, and so do the values of results,
+/// so that `Result<Worker, Error>` becomes `Result<WorkerMock, Error>`. For any other type only the
+/// trait objects are mocked, so that `Handle<dyn Clock>` becomes `Handle<ClockMock>`.
+fn mock_generic_arguments(path: &TypePath, name: &Ident, naming: &Naming) -> Option<MockPrepared> {
+    let mut path = path.clone();
+    let segment = path.path.segments.last_mut()?;
+    let is_container = CONTAINERS
+        .iter()
+        .any(|container| segment.ident == container);
+    let is_map = MAPS.iter().any(|map| segment.ident == map);
+    let is_result = RESULTS.iter().any(|result| segment.ident == result);
+    let PathArguments::AngleBracketed(arguments) = &mut segment.arguments else {
+        return None;
+    };
+
+    let types = arguments
+        .args
+        .iter_mut()
+        .filter_map(|argument| match argument {
+            GenericArgument::Type(ty) => Some(ty),
+            _ => None,
+        });
+    let mocked: Vec<&mut Type> = if is_map {
+        types.skip(1).take(1).collect()
+    } else if is_result {
+        types.take(1).collect()
+    } else if is_container {
+        types.collect()
+    } else {
+        types
+            .filter(|ty| matches!(ty, Type::TraitObject(_) | Type::ImplTrait(_)))
+            .collect()
+    };
+    if mocked.is_empty() {
+        return None;
+    }
+
+    let single = mocked.len() == 1;
+    let stream = mocked
+        .into_iter()
+        .enumerate()
+        .filter_map(|(i, ty)| {
+            let name = if single {
+                name.clone()
+            } else {
+                format_ident!("{name}{i}")
+            };
+            let prepared = get_mocking_candidate(ty, &name, naming);
+            *ty = prepared.mocked_type;
+            prepared.stream
+        })
+        .collect();
+
+    Some(MockPrepared::new(Type::Path(path), Some(stream)))
+}
+
+/// Mocks the elements of a tuple, keeping the tuple itself so that `(Db, Cache)` becomes
+/// `(DbMock, CacheMock)`. When `elements` is given, only the elements at those positions are
+/// replaced with their mocks.
+pub fn mock_tuple(
+    t: &TypeTuple,
+    name: &Ident,
+    elements: Option<&[usize]>,
+    naming: &Naming,
+) -> MockPrepared {
+    let mut tuple = t.clone();
+    let stream = tuple
+        .elems
+        .iter_mut()
+        .enumerate()
+        .filter(|(i, _)| elements.is_none_or(|elements| elements.contains(i)))
+        .filter_map(|(i, ty)| {
+            let prepared = get_mocking_candidate(ty, &format_ident!("{name}{i}"), naming);
+            *ty = prepared.mocked_type;
+            prepared.stream
+        })
+        .collect();
+
+    MockPrepared::new(Type::Tuple(tuple), Some(stream))
 }
 
 pub trait Traitified {
     fn bounds(&self) -> &Punctuated<TypeParamBound, Plus>;
+
+    fn as_type(&self) -> Type;
 }
 
 impl Traitified for TypeImplTrait {
     fn bounds(&self) -> &Punctuated<TypeParamBound, Plus> {
         &self.bounds
     }
+
+    fn as_type(&self) -> Type {
+        Type::ImplTrait(self.clone())
+    }
 }
 impl Traitified for TypeTraitObject {
     fn bounds(&self) -> &Punctuated<TypeParamBound, Plus> {
         &self.bounds
     }
+
+    fn as_type(&self) -> Type {
+        Type::TraitObject(self.clone())
+    }
 }
 
+#[cfg(test)]
 mod tests {
     use super::*;
+    use proc_macro2::Span;
 
     #[test]
     pub fn resolve_path_and_mock_for_single_segment() {
         let path = syn::TypePath {
+            attrs: vec![],
             qself: None,
             path: syn::parse_str("Foo").unwrap(),
         };
-        let mocked = resolve_path_and_mock(&path);
+        let mocked = resolve_path_and_mock(&path, &Naming::default());
         assert_eq!(mocked.mocked_type, syn::parse_str("FooMock").unwrap());
     }
 
+    #[test]
+    pub fn report_empty_path() {
+        let path = syn::TypePath {
+            attrs: vec![],
+            qself: None,
+            path: Path {
+                leading_colon: None,
+                segments: Punctuated::new(),
+            },
+        };
+        let mocked = resolve_path_and_mock(&path, &Naming::default());
+        assert!(mocked.stream.is_some());
+    }
+
     #[test]
     pub fn resolve_multi_segment() {
         let path = syn::TypePath {
+            attrs: vec![],
             qself: None,
             path: syn::parse_str("qow::Fow").unwrap(),
         };
-        let mocked = resolve_path_and_mock(&path);
+        let mocked = resolve_path_and_mock(&path, &Naming::default());
         assert_eq!(mocked.mocked_type, syn::parse_str("qow::FowMock").unwrap());
     }
+
+    #[test]
+    pub fn mock_trait_object_in_box() {
+        let ty = syn::parse_str("Box<dyn time::Clock>").unwrap();
+        let mocked = get_mocking_candidate(
+            &ty,
+            &Ident::new("Foo", Span::call_site()),
+            &Naming::default(),
+        );
+        assert_eq!(
+            mocked.mocked_type,
+            syn::parse_str("Box<time::ClockMock>").unwrap()
+        );
+    }
+
+    #[test]
+    pub fn mock_trait_object_with_auto_traits() {
+        let ty = syn::parse_str("Arc<dyn Repo + Send + Sync + 'static>").unwrap();
+        let mocked = get_mocking_candidate(
+            &ty,
+            &Ident::new("Foo", Span::call_site()),
+            &Naming::default(),
+        );
+        assert_eq!(mocked.mocked_type, syn::parse_str("Arc<RepoMock>").unwrap());
+    }
+
+    #[test]
+    pub fn mock_tuple_elements() {
+        let Type::Tuple(tuple) = syn::parse_str("(Db, Cache, u32)").unwrap() else {
+            unreachable!()
+        };
+        let name = Ident::new("Foo", Span::call_site());
+        let mocked = mock_tuple(&tuple, &name, Some(&[0, 1]), &Naming::default());
+        assert_eq!(
+            mocked.mocked_type,
+            syn::parse_str("(DbMock, CacheMock, u32)").unwrap()
+        );
+    }
+
+    #[test]
+    pub fn mock_reference_keeps_lifetime_and_mutability() {
+        let ty = syn::parse_str("&'a mut Db").unwrap();
+        let mocked = get_mocking_candidate(
+            &ty,
+            &Ident::new("Foo", Span::call_site()),
+            &Naming::default(),
+        );
+        assert_eq!(
+            mocked.mocked_type,
+            syn::parse_str("&'a mut DbMock").unwrap()
+        );
+    }
+
+    #[test]
+    pub fn mock_pointer_keeps_kind() {
+        let ty = syn::parse_str("*const ffi::Handle").unwrap();
+        let mocked = get_mocking_candidate(
+            &ty,
+            &Ident::new("Foo", Span::call_site()),
+            &Naming::default(),
+        );
+        assert_eq!(
+            mocked.mocked_type,
+            syn::parse_str("*const ffi::HandleMock").unwrap()
+        );
+    }
+
+    #[test]
+    pub fn mock_containers_keep_their_type() {
+        let name = Ident::new("Foo", Span::call_site());
+        for (ty, expected) in [
+            ("[Worker; 4]", "[WorkerMock; 4]"),
+            ("&[Worker]", "&[WorkerMock]"),
+            ("Vec<Worker>", "Vec<WorkerMock>"),
+            ("Option<Rc<Worker>>", "Option<Rc<WorkerMock>>"),
+            (
+                "std::collections::HashMap<String, Worker>",
+                "std::collections::HashMap<String, WorkerMock>",
+            ),
+            ("Pool<Worker>", "PoolMock<Worker>"),
+        ] {
+            let mocked =
+                get_mocking_candidate(&syn::parse_str(ty).unwrap(), &name, &Naming::default());
+            assert_eq!(mocked.mocked_type, syn::parse_str(expected).unwrap());
+        }
+    }
+
+    #[test]
+    pub fn mock_associated_type_through_trait() {
+        let name = Ident::new("Foo", Span::call_site());
+        for (ty, expected) in [
+            ("<B as Backend>::Conn", "<BackendMock as Backend>::Conn"),
+            (
+                "<B as db::Backend<u8>>::Conn",
+                "<db::BackendMock<u8> as db::Backend<u8>>::Conn",
+            ),
+            ("<Db>::Conn", "<DbMock>::Conn"),
+        ] {
+            let mocked =
+                get_mocking_candidate(&syn::parse_str(ty).unwrap(), &name, &Naming::default());
+            assert_eq!(mocked.mocked_type, syn::parse_str(expected).unwrap());
+        }
+    }
+
+    #[test]
+    pub fn report_combined_traits() {
+        let ty = syn::parse_str("impl Read + Write + ?Sized").unwrap();
+        let mocked = get_mocking_candidate(
+            &ty,
+            &Ident::new("Foo", Span::call_site()),
+            &Naming::default(),
+        );
+        assert_eq!(mocked.mocked_type, ty);
+        assert!(
+            mocked
+                .stream
+                .is_some_and(|stream| stream.to_string().contains("compile_error"))
+        );
+    }
+
+    #[test]
+    pub fn check_the_auto_traits_of_generic_traits() {
+        let bounds: TypeTraitObject = syn::parse_str("dyn Repo<Entity = ()> + Send").unwrap();
+        let check = implement_trait_for_mock(&parse_quote!(RepoMock), &bounds.bounds).to_string();
+        assert!(check.contains("Send"));
+        assert!(!check.contains("Entity"));
+    }
 }
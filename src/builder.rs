@@ -0,0 +1,201 @@
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote};
+use syn::{
+    Attribute, Field, Fields, Generics, Ident, Index, ItemEnum, Member, Variant, Visibility,
+};
+
+use crate::record::is_instance_field;
+use crate::toffel::replaced_type;
+
+/// Generates `mock_with`, which builds the mock from `mock_new` and lets a closure change it.
+pub fn generate_mock_with(name: &Ident, generics: &Generics) -> TokenStream {
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    quote! {
+        impl #impl_generics #name #ty_generics #where_clause {
+            /// Builds the mock from `mock_new` and changes it through the closure.
+            pub fn mock_with(change: impl FnOnce(&mut Self)) -> Self {
+                let mut mock = Self::mock_new();
+                change(&mut mock);
+                mock
+            }
+        }
+    }
+}
+
+/// Generates `builder` for the mock of a struct, which returns a `<Name>Builder` starting from
+/// `mock_new`, with a function for each field to override its value. Named fields keep their
+/// name, and the fields of tuple structs are set through `field_<position>`. Fields marked with
+/// `#[mocked]` take the type that the `#[toffel]` among `attrs` gives them in the mock.
+pub fn generate_struct_builder(
+    attrs: &[Attribute],
+    vis: &Visibility,
+    name: &Ident,
+    generics: &Generics,
+    fields: &Fields,
+) -> TokenStream {
+    let builder = format_ident!("{name}Builder");
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let setters = settable(fields).map(|(position, field)| {
+        let setter = setter_name(field, position);
+        let member = field.ident.clone().map_or_else(
+            || {
+                Member::Unnamed(Index {
+                    index: u32::try_from(position).expect("there are only a few fields"),
+                    span: Span::call_site(),
+                })
+            },
+            Member::Named,
+        );
+        let ty = replaced_type(attrs, field, name, position);
+        quote! {
+            pub fn #setter(mut self, value: #ty) -> Self {
+                self.0.#member = value;
+                self
+            }
+        }
+    });
+
+    quote! {
+        #vis struct #builder #impl_generics (#name #ty_generics) #where_clause;
+
+        impl #impl_generics #name #ty_generics #where_clause {
+            /// Starts a builder from `mock_new`, of which the fields can be overridden one by one.
+            pub fn builder() -> #builder #ty_generics {
+                #builder(Self::mock_new())
+            }
+        }
+
+        impl #impl_generics #builder #ty_generics #where_clause {
+            #(#setters)*
+
+            pub fn build(self) -> #name #ty_generics {
+                self.0
+            }
+        }
+    }
+}
+
+/// Generates a `<variant>_builder` function for each variant with fields of the mock of an enum,
+/// which returns a `<Name><Variant>Builder` starting from the variant built with the values of
+/// `#[mocked_with(value)]` on its fields and the default values of the others.
+pub fn generate_variant_builders(
+    enoom: &ItemEnum,
+    name: &Ident,
+    constructors: &[TokenStream],
+) -> TokenStream {
+    let vis = &enoom.vis;
+    let (impl_generics, ty_generics, where_clause) = enoom.generics.split_for_impl();
+
+    enoom
+        .variants
+        .iter()
+        .zip(constructors)
+        .filter(|(variant, _)| !variant.fields.is_empty())
+        .map(|(variant, constructor)| {
+            let builder = format_ident!("{name}{}Builder", variant.ident);
+            let start = Ident::new(
+                &format!("{}_builder", snake_case(&variant.ident)),
+                Span::call_site(),
+            );
+            let setters = settable(&variant.fields).map(|(position, field)| {
+                variant_setter(&enoom.attrs, name, variant, field, position)
+            });
+
+            quote! {
+                #vis struct #builder #impl_generics (#name #ty_generics) #where_clause;
+
+                impl #impl_generics #name #ty_generics #where_clause {
+                    /// Starts a builder from this variant, of which the fields can be overridden
+                    /// one by one.
+                    pub fn #start() -> #builder #ty_generics {
+                        #builder(#constructor)
+                    }
+                }
+
+                impl #impl_generics #builder #ty_generics #where_clause {
+                    #(#setters)*
+
+                    pub fn build(self) -> #name #ty_generics {
+                        self.0
+                    }
+                }
+            }
+        })
+        .collect()
+}
+
+/// The function overriding a field of a variant. The builder always holds the variant it was
+/// started from, so the other variants cannot be reached.
+fn variant_setter(
+    attrs: &[Attribute],
+    name: &Ident,
+    variant: &Variant,
+    field: &Field,
+    position: usize,
+) -> TokenStream {
+    let variant_name = &variant.ident;
+    let setter = setter_name(field, position);
+    let ty = replaced_type(
+        attrs,
+        field,
+        &format_ident!("{name}{variant_name}"),
+        position,
+    );
+    let pattern = if let Some(ident) = &field.ident {
+        quote!(#variant_name { #ident: field, .. })
+    } else {
+        let skipped = (0..position).map(|_| quote!(_));
+        quote!(#variant_name(#(#skipped,)* field, ..))
+    };
+
+    quote! {
+        pub fn #setter(mut self, value: #ty) -> Self {
+            let Self(#name::#pattern) = &mut self else {
+                unreachable!("the builder holds the variant it was started from")
+            };
+            *field = value;
+            self
+        }
+    }
+}
+
+/// The fields with their position which get a function to set them, leaving out the hidden field
+/// of the mock.
+fn settable(fields: &Fields) -> impl Iterator<Item = (usize, &Field)> {
+    fields
+        .iter()
+        .enumerate()
+        .filter(|(_, field)| !is_instance_field(field))
+}
+
+/// The name of the function setting a field, which is the name of the field or `field_<position>`
+/// for the fields of tuples.
+fn setter_name(field: &Field, position: usize) -> Ident {
+    field
+        .ident
+        .clone()
+        .unwrap_or_else(|| format_ident!("field_{position}"))
+}
+
+/// The name in snake case, keeping runs of capitals together so that `HTTPError` becomes
+/// `http_error`.
+pub fn snake_case(ident: &Ident) -> String {
+    let chars: Vec<char> = ident.to_string().chars().collect();
+    chars
+        .iter()
+        .enumerate()
+        .fold(String::new(), |mut snake, (i, c)| {
+            let previous = i.checked_sub(1).map(|i| chars[i]);
+            let next = chars.get(i + 1);
+            let starts_word = c.is_uppercase()
+                && previous.is_some_and(|previous| {
+                    !previous.is_uppercase() || next.is_some_and(|next| next.is_lowercase())
+                });
+            if starts_word && !snake.ends_with('_') {
+                snake.push('_');
+            }
+            snake.extend(c.to_lowercase());
+            snake
+        })
+}
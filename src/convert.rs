@@ -0,0 +1,276 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{
+    Fields, GenericArgument, Generics, Ident, Item, PathArguments, PathSegment, Type, TypePath,
+};
+
+use crate::diagnostics::{Diagnostics, unsupported};
+
+/// Generates `From` in both directions between a struct or an enum and its mock, as asked with
+/// `#[mock(convert)]`. The fields keep their value, and those marked with `#[mocked]`, which hold
+/// mocks in the mock, are converted along with them. The fields whose value cannot be converted
+/// are reported instead.
+pub fn generate_conversions(original: &Item, mock_name: &Ident) -> TokenStream {
+    let (name, generics) = match original {
+        Item::Struct(s) => (&s.ident, &s.generics),
+        Item::Enum(e) => (&e.ident, &e.generics),
+        _ => unreachable!("only structs and enums are converted"),
+    };
+
+    // Both directions convert the same fields, so the errors are only collected once.
+    let mut diagnostics = Diagnostics::default();
+    let into_mock = conversion(original, name, mock_name, generics, true, &mut diagnostics);
+    if !diagnostics.is_empty() {
+        return diagnostics.into_compile_errors();
+    }
+    let into_original = conversion(original, mock_name, name, generics, false, &mut diagnostics);
+    quote! {
+        #into_mock
+        #into_original
+    }
+}
+
+/// Implements `From<from>` for `to` by moving the fields out of `from`. A struct converted
+/// `into_mock` starts a new instance of the mock.
+fn conversion(
+    original: &Item,
+    from: &Ident,
+    to: &Ident,
+    generics: &Generics,
+    into_mock: bool,
+    diagnostics: &mut Diagnostics,
+) -> TokenStream {
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let body = match original {
+        Item::Struct(s) => {
+            let (pattern, construct) = convert_fields(
+                &s.fields,
+                &quote!(#from),
+                &quote!(Self),
+                into_mock,
+                diagnostics,
+            );
+            quote! {
+                let #pattern = value;
+                #construct
+            }
+        }
+        Item::Enum(e) => {
+            let arms = e.variants.iter().map(|variant| {
+                let variant_name = &variant.ident;
+                let (pattern, construct) = convert_fields(
+                    &variant.fields,
+                    &quote!(#from::#variant_name),
+                    &quote!(Self::#variant_name),
+                    false,
+                    diagnostics,
+                );
+                quote!(#pattern => #construct,)
+            });
+            quote! {
+                match value {
+                    #(#arms)*
+                }
+            }
+        }
+        _ => unreachable!("only structs and enums are converted"),
+    };
+
+    quote! {
+        impl #impl_generics ::std::convert::From<#from #ty_generics> for #to #ty_generics
+        #where_clause
+        {
+            fn from(value: #from #ty_generics) -> Self {
+                #body
+            }
+        }
+    }
+}
+
+/// The pattern binding the fields of `from` by value, and the expression building `to` out of
+/// them. The hidden field of the mock is left out of the pattern, and given a new instance when
+/// `instance` is set.
+fn convert_fields(
+    fields: &Fields,
+    from: &TokenStream,
+    to: &TokenStream,
+    instance: bool,
+    diagnostics: &mut Diagnostics,
+) -> (TokenStream, TokenStream) {
+    let bindings: Vec<Ident> = fields
+        .iter()
+        .enumerate()
+        .map(|(position, field)| {
+            field
+                .ident
+                .clone()
+                .unwrap_or_else(|| format_ident!("__field{position}"))
+        })
+        .collect();
+    let values = fields.iter().zip(&bindings).map(|(field, binding)| {
+        if field
+            .attrs
+            .iter()
+            .any(|attr| attr.path().is_ident("mocked"))
+        {
+            convert_value(&quote!(#binding), &field.ty).unwrap_or_else(|error| {
+                diagnostics.push(error);
+                TokenStream::new()
+            })
+        } else {
+            quote!(#binding)
+        }
+    });
+
+    match fields {
+        Fields::Named(_) => {
+            let instance =
+                instance.then(|| quote!(__mocktoffel: ::std::default::Default::default(),));
+            (
+                quote!(#from { #(#bindings,)* .. }),
+                quote!(#to { #(#bindings: #values,)* #instance }),
+            )
+        }
+        Fields::Unnamed(_) => (quote!(#from(#(#bindings),*)), quote!(#to(#(#values),*))),
+        Fields::Unit => (quote!(#from), quote!(#to)),
+    }
+}
+
+/// Converts the value of a field marked with `#[mocked]`, whose type is mocked the way
+/// `#[mocked]` mocks it: the containers of the standard library are rebuilt with their items
+/// converted, so that a `Vec<Db>` becomes a `Vec<DbMock>`, and any other value goes through
+/// `Into`. Shared pointers are rebuilt from their value, cloned if it is still shared, and the
+/// containers that do not own their value cannot be converted.
+fn convert_value(value: &TokenStream, ty: &Type) -> syn::Result<TokenStream> {
+    let Type::Path(TypePath {
+        qself: None, path, ..
+    }) = ty
+    else {
+        return Ok(quote!(::std::convert::Into::into(#value)));
+    };
+    let Some(segment) = path.segments.last() else {
+        return Ok(quote!(::std::convert::Into::into(#value)));
+    };
+    let types = type_arguments(segment);
+    let item = quote!(__item);
+    let convert = |ty: &Type| convert_value(&item, ty);
+
+    let converted = match (segment.ident.to_string().as_str(), types.as_slice()) {
+        ("Option" | "Result", [ty, ..]) => {
+            let convert = convert(ty)?;
+            quote!(#value.map(|#item| #convert))
+        }
+        ("Vec" | "VecDeque" | "LinkedList" | "BTreeSet" | "HashSet" | "BinaryHeap", [ty, ..]) => {
+            let convert = convert(ty)?;
+            quote!(#value.into_iter().map(|#item| #convert).collect())
+        }
+        ("BTreeMap" | "HashMap", [_, ty, ..]) => {
+            let convert = convert(ty)?;
+            quote!(#value.into_iter().map(|(key, #item)| (key, #convert)).collect())
+        }
+        ("Reverse", [ty]) => {
+            let convert = convert_value(&quote!(#value.0), ty)?;
+            quote!(::std::cmp::Reverse(#convert))
+        }
+        ("Cow", [ty]) => {
+            let convert = convert_value(&quote!(#value.into_owned()), ty)?;
+            quote!(::std::borrow::Cow::Owned(#convert))
+        }
+        ("ManuallyDrop", [ty]) => {
+            let convert = convert_value(&quote!(::std::mem::ManuallyDrop::into_inner(#value)), ty)?;
+            quote!(::std::mem::ManuallyDrop::new(#convert))
+        }
+        ("Box", [ty, ..]) => {
+            let convert = convert_value(&quote!(*#value), ty)?;
+            quote!(::std::boxed::Box::new(#convert))
+        }
+        (pointer @ ("Rc" | "Arc"), [ty]) => {
+            let module = format_ident!("{}", if pointer == "Rc" { "rc" } else { "sync" });
+            let pointer = format_ident!("{pointer}");
+            let unwrapped = quote!(::std::#module::#pointer::unwrap_or_clone(#value));
+            let convert = convert_value(&unwrapped, ty)?;
+            quote!(::std::#module::#pointer::new(#convert))
+        }
+        ("Pin", [pointer]) => {
+            let Some(ty) = boxed_item(pointer) else {
+                return Err(unsupported(
+                    ty,
+                    "only a `Pin<Box<_>>` can be converted, as it owns its value",
+                ));
+            };
+            let convert = convert_value(&quote!(*::std::pin::Pin::into_inner(#value)), ty)?;
+            quote!(::std::boxed::Box::pin(#convert))
+        }
+        ("Weak", _) => {
+            return Err(unsupported(
+                ty,
+                "a `Weak` cannot be converted, as it does not own its value",
+            ));
+        }
+        (container @ ("Cell" | "RefCell" | "UnsafeCell"), [ty]) => {
+            let container = format_ident!("{container}");
+            let convert = convert_value(&quote!(#value.into_inner()), ty)?;
+            quote!(::std::cell::#container::new(#convert))
+        }
+        (container @ ("OnceCell" | "OnceLock"), [ty]) => {
+            let module = if container == "OnceCell" {
+                quote!(cell)
+            } else {
+                quote!(sync)
+            };
+            let container = format_ident!("{container}");
+            let convert = convert(ty)?;
+            quote! {{
+                let cell = ::std::#module::#container::new();
+                if let ::std::option::Option::Some(#item) = #value.into_inner() {
+                    let _ = cell.set(#convert);
+                }
+                cell
+            }}
+        }
+        (container @ ("Mutex" | "RwLock"), [ty]) => {
+            let container = format_ident!("{container}");
+            let convert = convert_value(
+                &quote! {
+                    #value
+                        .into_inner()
+                        .unwrap_or_else(::std::sync::PoisonError::into_inner)
+                },
+                ty,
+            )?;
+            quote!(::std::sync::#container::new(#convert))
+        }
+        _ => quote!(::std::convert::Into::into(#value)),
+    };
+    Ok(converted)
+}
+
+/// The types among the generic arguments of the last segment of a path.
+fn type_arguments(segment: &PathSegment) -> Vec<&Type> {
+    let PathArguments::AngleBracketed(arguments) = &segment.arguments else {
+        return Vec::new();
+    };
+    arguments
+        .args
+        .iter()
+        .filter_map(|argument| match argument {
+            GenericArgument::Type(ty) => Some(ty),
+            _ => None,
+        })
+        .collect()
+}
+
+/// The item of a `Box`, which is the only pointer that a `Pin` can be converted through.
+fn boxed_item(pointer: &Type) -> Option<&Type> {
+    let Type::Path(TypePath {
+        qself: None, path, ..
+    }) = pointer
+    else {
+        return None;
+    };
+    let segment = path
+        .segments
+        .last()
+        .filter(|segment| segment.ident == "Box")?;
+    type_arguments(segment).first().copied()
+}
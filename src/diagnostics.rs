@@ -0,0 +1,41 @@
+use std::fmt::Display;
+
+use quote::ToTokens;
+
+/// Collects the errors found while expanding an item so that all of them are reported at once,
+/// instead of stopping at the first one.
+#[derive(Default)]
+pub struct Diagnostics {
+    errors: Option<syn::Error>,
+}
+
+impl Diagnostics {
+    pub fn push(&mut self, error: syn::Error) {
+        match &mut self.errors {
+            Some(errors) => errors.combine(error),
+            None => self.errors = Some(error),
+        }
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.errors.is_none()
+    }
+
+    /// Reports `tokens` as a construct that cannot be mocked.
+    pub fn unsupported(&mut self, tokens: impl ToTokens, message: impl Display) {
+        self.push(unsupported(tokens, message));
+    }
+
+    /// The `compile_error!` invocations for the collected errors, or nothing if there are none.
+    pub fn into_compile_errors(self) -> proc_macro2::TokenStream {
+        self.errors
+            .map(|errors| errors.to_compile_error())
+            .unwrap_or_default()
+    }
+}
+
+/// An error pointing at `tokens`, which cannot be mocked. The message should hint at what is
+/// supported instead.
+pub fn unsupported(tokens: impl ToTokens, message: impl Display) -> syn::Error {
+    syn::Error::new_spanned(tokens, message)
+}
@@ -1,26 +1,37 @@
 use crate::branch::Traitified;
+use crate::builder::{generate_mock_with, generate_struct_builder, generate_variant_builders};
+use crate::diagnostics::{Diagnostics, unsupported};
+use crate::options::Naming;
 use proc_macro::TokenStream;
 use proc_macro2::Span;
-use quote::quote;
+use quote::{ToTokens, quote};
 use std::fmt::Write;
 use syn::{
-    AngleBracketedGenericArguments, Expr, GenericParam, Generics, Ident, Item, ItemEnum,
-    ItemStruct, Meta, Path, PathArguments, TraitBoundModifiers, Type, TypeParamBound, TypePath,
-    punctuated::Punctuated,
+    AngleBracketedGenericArguments, Attribute, Expr, Field, Fields, Ident, Item, ItemEnum,
+    ItemStruct, ItemUnion, LitInt, Meta, Path, PathArguments, Token, TraitBoundModifiers, Type,
+    TypeParamBound, TypePath, Variant, parse::ParseStream, parse_quote, punctuated::Punctuated,
 };
 
 pub struct MockPrepared {
     pub mocked_type: Type,
-    stream: Option<TokenStream>,
+    pub stream: Option<proc_macro2::TokenStream>,
 }
 
 impl MockPrepared {
-    pub const fn new(mocked_type: Type, stream: Option<TokenStream>) -> Self {
+    pub const fn new(mocked_type: Type, stream: Option<proc_macro2::TokenStream>) -> Self {
         Self {
             mocked_type,
             stream,
         }
     }
+
+    /// Keeps the type as it is and reports it as a type that cannot be mocked.
+    pub fn unsupported(ty: &Type, message: &str) -> Self {
+        Self::new(
+            ty.clone(),
+            Some(unsupported(ty, message).to_compile_error()),
+        )
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -44,81 +55,91 @@ impl Extracted {
 
 #[allow(clippy::module_name_repetitions)]
 pub trait ExtractName {
-    fn extract_name(&self) -> Extracted;
+    fn extract_name(&self) -> syn::Result<Extracted>;
 }
 
 impl ExtractName for Type {
-    fn extract_name(&self) -> Extracted {
+    fn extract_name(&self) -> syn::Result<Extracted> {
         match self {
-            Self::Array(_) => todo!(),
-            Self::FnPtr(f) => todo!(),
-            Self::Group(g) => todo!(),
-            Self::ImplTrait(im) => Extracted::with_ident(extract_name_for_bounds(im)),
-            Self::Infer(inf) => unreachable!(),
-            Self::Macro(m) => todo!(),
-            Self::Paren(p) => todo!(),
+            Self::Group(g) => g.elem.extract_name(),
+            Self::ImplTrait(im) => Ok(Extracted::with_ident(extract_name_for_bounds(im))),
+            Self::Paren(p) => p.elem.extract_name(),
             Self::Path(TypePath {
-                qself: None,
-                path,
-                attrs,
+                qself: None, path, ..
             }) => path.extract_name(),
-            Self::Ptr(p) => todo!(),
-            _ => todo!(),
+            _ => Err(unsupported(
+                self,
+                "only named types such as `Foo` or `Foo<T>` can be mocked",
+            )),
         }
     }
 }
 
 impl ExtractName for Path {
-    fn extract_name(&self) -> Extracted {
-        let segment = self.segments.last().unwrap();
+    fn extract_name(&self) -> syn::Result<Extracted> {
+        let Some(segment) = self.segments.last() else {
+            return Err(unsupported(self, "expected the name of a type"));
+        };
         match &segment.arguments {
             PathArguments::AngleBracketed(abga) => {
-                Extracted::new(segment.ident.clone(), Some(abga.clone()))
+                Ok(Extracted::new(segment.ident.clone(), Some(abga.clone())))
             }
-            PathArguments::None => {
-                Extracted::with_ident(self.segments.last().unwrap().ident.clone())
-            }
-
-            PathArguments::Parenthesized(_) => unreachable!(),
+            PathArguments::None => Ok(Extracted::with_ident(segment.ident.clone())),
+            PathArguments::Parenthesized(_) => Err(unsupported(
+                self,
+                "`Fn` traits cannot be mocked by name, mock a function pointer field instead",
+            )),
         }
     }
 }
 
-fn extract_name_for_bounds<T: Traitified>(imp: &T) -> Ident {
-    let name = imp
-        .bounds()
-        .iter()
-        .filter_map(|merkmal| {
-            if let TypeParamBound::Trait(trait_bound) = merkmal
-                && trait_bound.modifiers == TraitBoundModifiers::default()
-            {
-                Some(&trait_bound.path)
-            } else {
-                None
-            }
-        })
-        .fold(String::new(), |mut mock_name, path| {
-            let p: String = path
-                .segments
-                .iter()
-                .fold(String::new(), |mut full_bound, segment| {
-                    let _ = write!(full_bound, "{}", segment.ident);
-                    full_bound
-                });
+/// The traits of the bounds that a mock has to implement by hand. Auto traits, lifetimes and
+/// `?Sized` are left out as they hold for the mocks without any implementation.
+pub fn mockable_bounds<T: Traitified>(imp: &T) -> impl Iterator<Item = &Path> {
+    imp.bounds().iter().filter_map(|merkmal| {
+        if let TypeParamBound::Trait(trait_bound) = merkmal
+            && trait_bound.modifiers == TraitBoundModifiers::default()
+            && trait_bound.maybe.is_none()
+            && !is_auto_trait(&trait_bound.path)
+        {
+            Some(&trait_bound.path)
+        } else {
+            None
+        }
+    })
+}
 
-            mock_name.push_str(&p);
+fn is_auto_trait(path: &Path) -> bool {
+    path.segments.last().is_some_and(|segment| {
+        ["Send", "Sync", "Unpin", "UnwindSafe", "RefUnwindSafe"]
+            .iter()
+            .any(|auto| segment.ident == auto)
+    })
+}
 
-            mock_name
-        });
+pub fn extract_name_for_bounds<T: Traitified>(imp: &T) -> Ident {
+    let name = mockable_bounds(imp).fold(String::new(), |mut mock_name, path| {
+        let p: String = path
+            .segments
+            .iter()
+            .fold(String::new(), |mut full_bound, segment| {
+                let _ = write!(full_bound, "{}", segment.ident);
+                full_bound
+            });
+
+        mock_name.push_str(&p);
+
+        mock_name
+    });
     Ident::new(&name, Span::call_site())
 }
 
-pub fn prepare_mock_name(name: &Extracted) -> Extracted {
-    let inp = format!("{}Mock", name.name);
-    Extracted::new(Ident::new(&inp, Span::call_site()), name.generics.clone())
+pub fn prepare_mock_name(name: &Extracted, naming: &Naming) -> Extracted {
+    Extracted::new(naming.mock_name(&name.name), name.generics.clone())
 }
 
-pub fn parse_fields_and_generate_for_values(schtruct: &mut ItemStruct) -> TokenStream {
+pub fn parse_fields_and_generate_for_values(schtruct: &ItemStruct) -> TokenStream {
+    let mut diagnostics = Diagnostics::default();
     let (fields, values) =
         schtruct
             .fields
@@ -145,154 +166,280 @@ pub fn parse_fields_and_generate_for_values(schtruct: &mut ItemStruct) -> TokenS
                     (None, _) => {
                         values.push(quote!({ Default::default() }));
                     }
-                    _ => todo!(),
+                    (Some(v), ident) => {
+                        diagnostics.unsupported(
+                            v,
+                            "expected the value of the field as `#[mocked_with(value)]`",
+                        );
+                        fields.extend(ident.clone());
+                        values.push(quote!({ Default::default() }));
+                    }
                 }
 
                 (fields, values)
             });
 
+    if !diagnostics.is_empty() {
+        return TokenStream::from(diagnostics.into_compile_errors());
+    }
+
     let struct_name = &schtruct.ident;
-    let generics = &mut schtruct.generics;
-    let mut impl_generics = generics.clone();
-    extract_generics_from_bounds(&mut impl_generics);
-
-    let tok = match (&fields[..], &values[..]) {
-        (&[], &[]) => {
-            quote! {
-                impl #generics #struct_name #impl_generics {
-                    pub fn mock_new() -> Self {
-                        Self {}
-                    }
-                }
-            }
-        }
-        (&[], v) => {
-            quote! {
-                impl #generics #struct_name #impl_generics {
-                    pub fn mock_new() -> Self {
-                        Self(#(#values),*)
-                    }
-                }
-            }
-        }
-        (f, v) => {
-            quote! {
-                impl #impl_generics #struct_name #impl_generics {
-                    pub fn mock_new() -> Self {
-                        Self {
-                            #(#fields : #values),*
-                        }
-                    }
-                }
+    let (impl_generics, ty_generics, where_clause) = schtruct.generics.split_for_impl();
+    let mock_with = generate_mock_with(struct_name, &schtruct.generics);
+    let builder = generate_struct_builder(
+        &schtruct.attrs,
+        &schtruct.vis,
+        struct_name,
+        &schtruct.generics,
+        &schtruct.fields,
+    );
+    let constructor = match &schtruct.fields {
+        Fields::Named(_) => quote!(Self { #(#fields: #values),* }),
+        Fields::Unnamed(_) => quote!(Self(#(#values),*)),
+        Fields::Unit => quote!(Self),
+    };
+
+    TokenStream::from(quote! {
+        impl #impl_generics #struct_name #ty_generics #where_clause {
+            pub fn mock_new() -> Self {
+                #constructor
             }
         }
-    };
-    TokenStream::from(tok)
+
+        #mock_with
+        #builder
+    })
 }
 
-#[allow(clippy::option_if_let_else)]
-pub fn parse_fields_and_generate_variant(enoom: &mut ItemEnum) -> TokenStream {
+/// Generates `mock_new` for the mock of an enum. The variant it returns is picked in order from:
+///
+/// - a variant with `#[mocked_with = value]` or `#[mocked_with(value)]`, which is used as the
+///   value, or a plain `#[mocked_with]`, which builds the variant from the default values of its
+///   fields,
+/// - a variant with `#[mocked_with(value)]` on any of its fields, which is built from those values
+///   and the default values of its other fields,
+/// - `#[mocked_with = Enum::Variant]` on the enum itself,
+/// - the `Default` implementation of the enum.
+pub fn parse_fields_and_generate_variant(enoom: &mut ItemEnum, naming: &Naming) -> TokenStream {
+    let mut diagnostics = Diagnostics::default();
     let enum_name = enoom.ident.clone();
     let extracted = Extracted::with_ident(enum_name.clone());
-    let mocked = prepare_mock_name(&extracted);
+    let mocked = prepare_mock_name(&extracted, naming);
 
-    let mocked_name = &mocked.name;
-    let tok = if let Some(variant) = enoom.variants.iter_mut().find(|field| {
-        field.attrs.iter().any(|attr| {
-            if let Meta::NameValue(nv) = &attr.meta
-                && nv.path.is_ident("mocked_with")
-            {
-                true
-            } else {
-                false
-            }
-        })
-    }) {
-        let variant_name = &mut variant.ident;
-        if let Meta::NameValue(ref mut ml) = variant.attrs.first_mut().unwrap().meta {
-            let value = &mut ml.value;
-            replace_with_mocked(value, &enum_name, &mocked);
-            quote! {
-                impl #enum_name {
-                    pub fn mock_new() -> #mocked_name {
-                        #value
-                    }
+    let picked_variant = enoom.variants.iter_mut().find_map(|variant| {
+        let meta = variant
+            .attrs
+            .iter_mut()
+            .map(|attr| &mut attr.meta)
+            .find(|meta| meta.path().is_ident("mocked_with"))?;
+        let mut value = match meta {
+            Meta::NameValue(nv) => nv.value.clone(),
+            Meta::List(list) => match list.parse_args() {
+                Ok(value) => value,
+                Err(error) => {
+                    diagnostics.push(error);
+                    return Some(quote!(::std::unreachable!()));
                 }
-            }
-        } else {
-            quote! {
-                impl #enum_name {
-                    pub fn mock_new() -> #mocked_name {
-                        Self::#variant_name
-                    }
-                }
-            }
+            },
+            Meta::Path(_) => return Some(construct_variant(variant, &mut diagnostics)),
+        };
+        if let Err(error) = replace_with_mocked(&mut value, &enum_name, &mocked) {
+            diagnostics.push(error);
         }
-    } else if let (Some(variant), Some(field)) =
-        enoom
-            .variants
+        Some(value.to_token_stream())
+    });
+    let value = if let Some(value) = picked_variant {
+        value
+    } else if let Some(variant) = enoom.variants.iter().find(|variant| {
+        variant
+            .fields
             .iter()
-            .fold((None, None), |(picked_value, picked_field), variant| {
-                variant.fields.iter().fold(
-                    (picked_value, picked_field),
-                    |(picked_value, picked_field), field| {
-                        field.attrs.iter().fold(
-                            (picked_value, picked_field),
-                            |(picked_value, field), attr| {
-                                if let Meta::NameValue(nv) = &attr.meta
-                                    && nv.path.is_ident("mocked_with")
-                                {
-                                    (Some(variant), Some(nv.value.clone()))
-                                } else {
-                                    (None, None)
-                                }
-                            },
-                        )
-                    },
-                )
-            })
+            .any(|field| field.attrs.iter().any(is_mocked_with))
+    }) {
+        construct_variant(variant, &mut diagnostics)
+    } else if let Some(mocked_value) =
+        get_mocked_value_from_attributes(enoom, &mocked, &mut diagnostics)
     {
-        let variant_name = &variant.ident;
-        quote! {
-            impl #enum_name {
-                pub fn mock_new() -> #mocked_name {
-                    Self::#variant_name(#field)
-                }
+        mocked_value.to_token_stream()
+    } else {
+        quote!(Self::default())
+    };
+
+    let mocked_name = &mocked.name;
+    let (impl_generics, ty_generics, where_clause) = enoom.generics.split_for_impl();
+    let mock_with = generate_mock_with(mocked_name, &enoom.generics);
+    let constructors: Vec<_> = enoom
+        .variants
+        .iter()
+        // Invalid values are left to the diagnostics of `mock_new`, so they are not reported twice.
+        .map(|variant| construct_variant(variant, &mut Diagnostics::default()))
+        .collect();
+    let builders = generate_variant_builders(enoom, mocked_name, &constructors);
+    let errors = diagnostics.into_compile_errors();
+    TokenStream::from(quote! {
+        impl #impl_generics #mocked_name #ty_generics #where_clause {
+            pub fn mock_new() -> Self {
+                #value
             }
         }
-    } else if let Some(mocked_value) = get_mocked_value_from_attributes(enoom, &mocked) {
-        quote! {
-            impl #enum_name {
-                pub fn mock_new() -> #mocked_name {
-                    #mocked_value
-                }
-            }
+
+        #mock_with
+        #builders
+        #errors
+    })
+}
+
+/// Builds a variant of the mock of an enum, using the values given through `#[mocked_with(value)]`
+/// on its fields and the default value for the others.
+pub fn construct_variant(
+    variant: &Variant,
+    diagnostics: &mut Diagnostics,
+) -> proc_macro2::TokenStream {
+    let variant_name = &variant.ident;
+    let values = variant.fields.iter().map(|field| {
+        mocked_with_value(field, diagnostics).unwrap_or_else(|| quote!(Default::default()))
+    });
+
+    match &variant.fields {
+        Fields::Named(named) => {
+            let fields = named.named.iter().map(|field| &field.ident);
+            quote!(Self::#variant_name { #(#fields: #values),* })
         }
-    } else {
-        quote! {
-            impl #enum_name {
-                pub fn mock_new() -> Self {
-                   Self::default()
-                }
-            }
+        Fields::Unnamed(_) => quote!(Self::#variant_name(#(#values),*)),
+        Fields::Unit => quote!(Self::#variant_name),
+    }
+}
+
+/// Generates `mock_new` for the mock of a union. It initialises the field marked with
+/// `#[mocked_with(value)]`, or the first field with its default value.
+pub fn parse_fields_and_generate_for_union(union: &ItemUnion) -> TokenStream {
+    let mut diagnostics = Diagnostics::default();
+    let mut marked = union
+        .fields
+        .named
+        .iter()
+        .filter(|field| field.attrs.iter().any(is_mocked_with));
+    let initialised = match (marked.next(), marked.next()) {
+        (Some(field), None) => Some((
+            field,
+            mocked_with_value(field, &mut diagnostics).unwrap_or_default(),
+        )),
+        (Some(_), Some(other)) => {
+            diagnostics.unsupported(
+                other,
+                "only one field of a union can be initialised with `#[mocked_with(value)]`",
+            );
+            None
         }
+        (None, _) => union
+            .fields
+            .named
+            .first()
+            .map(|field| (field, quote!(Default::default()))),
+    };
+    let Some((field, value)) = initialised else {
+        diagnostics.unsupported(&union.fields, "unions need at least one field to be mocked");
+        return TokenStream::from(diagnostics.into_compile_errors());
     };
+    if !diagnostics.is_empty() {
+        return TokenStream::from(diagnostics.into_compile_errors());
+    }
 
-    TokenStream::from(tok)
+    let union_name = &union.ident;
+    let field = &field.ident;
+    let (impl_generics, ty_generics, where_clause) = union.generics.split_for_impl();
+    let mock_with = generate_mock_with(union_name, &union.generics);
+    TokenStream::from(quote! {
+        impl #impl_generics #union_name #ty_generics #where_clause {
+            pub fn mock_new() -> Self {
+                Self { #field: #value }
+            }
+        }
+
+        #mock_with
+    })
 }
 
-pub fn extract_generics_from_bounds(bounds: &mut Generics) {
-    bounds.params.iter_mut().for_each(|bound| match bound {
-        GenericParam::Type(ty) => {
-            ty.colon_token = None;
-            ty.bounds = Punctuated::new();
+/// The value given to a field through `#[mocked_with(value)]`.
+fn mocked_with_value(
+    field: &Field,
+    diagnostics: &mut Diagnostics,
+) -> Option<proc_macro2::TokenStream> {
+    match &field.attrs.iter().find(|attr| is_mocked_with(attr))?.meta {
+        Meta::List(list) => Some(list.tokens.clone()),
+        Meta::NameValue(nv) => Some(nv.value.to_token_stream()),
+        meta @ Meta::Path(_) => {
+            diagnostics.unsupported(
+                meta,
+                "expected the value of the field as `#[mocked_with(value)]`",
+            );
+            None
         }
-        GenericParam::Const(c) => todo!(),
-        GenericParam::Lifetime(lp) => {}
-    });
+    }
+}
+
+fn is_mocked_with(attr: &Attribute) -> bool {
+    attr.meta.path().is_ident("mocked_with")
+}
+
+/// Removes the `#[mocked_with(value)]` attribute from the list and returns its value.
+pub fn take_mocked_with(attrs: &mut Vec<Attribute>) -> Option<proc_macro2::TokenStream> {
+    let position = attrs
+        .iter()
+        .position(|attr| attr.meta.path().is_ident("mocked_with"))?;
+    match attrs.remove(position).meta {
+        Meta::List(list) => Some(list.tokens),
+        _ => None,
+    }
 }
 
-pub fn get_mocked_value_from_attributes(enoom: &mut ItemEnum, mocked: &Extracted) -> Option<Expr> {
+/// What a `#[mocked]` attribute on a field of a `#[toffel]` type asks for.
+pub enum MockedField {
+    /// A plain `#[mocked]`, which replaces the type with its mock.
+    Whole,
+    /// `#[mocked(0, 2)]`, which mocks the elements of a tuple at those positions.
+    Elements(Vec<usize>),
+    /// `#[mocked(with = FakeFoo)]`, which replaces the type with the given one.
+    With(Box<Type>),
+    /// `#[mocked(foreign)]`, which replaces the type with the mock of the same name in scope, such
+    /// as the ones generated by `mock_foreign!`.
+    Foreign,
+}
+
+/// Parses the `#[mocked]` attribute of a field.
+pub fn mocked_field(attr: &Attribute) -> syn::Result<MockedField> {
+    let Meta::List(list) = &attr.meta else {
+        return Ok(MockedField::Whole);
+    };
+    list.parse_args_with(|input: ParseStream<'_>| {
+        if input.peek(LitInt) {
+            return Punctuated::<LitInt, Token![,]>::parse_terminated(input)?
+                .iter()
+                .map(LitInt::base10_parse)
+                .collect::<syn::Result<_>>()
+                .map(MockedField::Elements);
+        }
+        let key: Ident = input.parse()?;
+        if key == "foreign" {
+            return Ok(MockedField::Foreign);
+        }
+        if key != "with" {
+            return Err(syn::Error::new(
+                key.span(),
+                "expected `#[mocked(with = Type)]`, `#[mocked(foreign)]` or the positions of \
+                 tuple elements",
+            ));
+        }
+        input.parse::<Token![=]>()?;
+        input.parse().map(|ty| MockedField::With(Box::new(ty)))
+    })
+}
+
+pub fn get_mocked_value_from_attributes(
+    enoom: &mut ItemEnum,
+    mocked: &Extracted,
+    diagnostics: &mut Diagnostics,
+) -> Option<Expr> {
     let name = &enoom.ident;
     enoom
         .attrs
@@ -301,28 +448,33 @@ pub fn get_mocked_value_from_attributes(enoom: &mut ItemEnum, mocked: &Extracted
             Meta::NameValue(nv)
                 if let Some(_) = nv.path.get_ident().map(|x| *x == "mocked_with") =>
             {
-                replace_with_mocked(&mut nv.value, name, mocked);
-                let path = &nv.value;
-                Some(syn::parse(TokenStream::from(quote!(<#path>))).unwrap())
+                if let Err(error) = replace_with_mocked(&mut nv.value, name, mocked) {
+                    diagnostics.push(error);
+                }
+                Some(nv.value.clone())
             }
             Meta::NameValue(nv)
                 if let Some(_) = nv.path.get_ident().map(|x| *x == "mocked_with_default") =>
             {
-                Some(syn::parse(TokenStream::from(quote!(<#name>::default()))).unwrap())
+                Some(parse_quote!(<#name>::default()))
             }
 
             _ => None,
         })
 }
 
-pub fn clean_out_attributes(item: &mut Item) {
+/// Removes the attributes of the mock macros from the fields and variants of a struct, union or
+/// enum, reporting any other item.
+pub fn clean_out_attributes(item: &mut Item, diagnostics: &mut Diagnostics) {
     match item {
         Item::Struct(s) => {
             s.fields.iter_mut().for_each(|field| {
                 field.attrs = field
                     .attrs
                     .extract_if(.., |attr| {
-                        if let Meta::Path(ref p) = attr.meta {
+                        if attr.path().is_ident("doc") {
+                            true
+                        } else if let Meta::Path(ref p) = attr.meta {
                             p.get_ident().map(|x| *x == "mocked").is_some()
                         } else {
                             false
@@ -331,77 +483,55 @@ pub fn clean_out_attributes(item: &mut Item) {
                     .collect();
             });
         }
+        Item::Union(u) => {
+            u.fields.named.iter_mut().for_each(|field| {
+                field.attrs.retain(|attr| !is_mocked_with(attr));
+            });
+        }
         Item::Enum(e) => {
             e.attrs.retain(|attr| {
-                if let Meta::NameValue(p) = &attr.meta
-                    && p.path.get_ident().map(|x| *x == "mocked_with").is_some()
-                {
-                    false
-                } else {
-                    true
-                }
+                !is_mocked_with(attr) && !attr.meta.path().is_ident("mocked_with_default")
             });
 
-            e.variants.iter_mut().for_each(|field| {
-                field.attrs = field
-                    .attrs
-                    .iter()
-                    .filter_map(|attr| {
-                        if let Meta::NameValue(ref p) = attr.meta
-                            && p.path.get_ident().map(|x| *x == "mocked_with").is_some()
-                        {
-                            None
-                        } else {
-                            Some(attr.clone())
-                        }
-                    })
-                    .collect();
-
-                field.fields.iter_mut().for_each(|field| {
-                    field.attrs = field
-                        .attrs
-                        .iter()
-                        .filter_map(|attr| {
-                            if let Meta::NameValue(ref p) = attr.meta
-                                && p.path.get_ident().map(|x| *x == "mocked_with").is_some()
-                            {
-                                None
-                            } else {
-                                Some(attr.clone())
-                            }
-                        })
-                        .collect();
+            e.variants.iter_mut().for_each(|variant| {
+                variant.attrs.retain(|attr| !is_mocked_with(attr));
+                variant.fields.iter_mut().for_each(|field| {
+                    field.attrs.retain(|attr| !is_mocked_with(attr));
                 });
             });
         }
-        _ => unreachable!(),
+        other => diagnostics.unsupported(
+            other,
+            "`#[mock]` can only be used on structs, enums, unions and traits",
+        ),
     }
 }
 
-fn replace_with_mocked(expr: &mut Expr, name: &Ident, mocked: &Extracted) {
-    match expr {
-        Expr::Path(path) => {
-            let mocked_segment = path
-                .path
-                .segments
-                .iter_mut()
-                .find(|path| path.ident == *name)
-                .unwrap();
-            mocked_segment.ident = mocked.name.clone();
-        }
-
-        Expr::Call(call) => {
-            if let Expr::Path(p) = call.func.as_mut() {
-                let mocked_segment = p
-                    .path
-                    .segments
-                    .iter_mut()
-                    .find(|path| path.ident == *name)
-                    .unwrap();
-                mocked_segment.ident = mocked.name.clone();
-            }
+/// Points the path of the variant in `expr` at the mock, so that `Foo::Bar` becomes
+/// `FooMock::Bar`.
+fn replace_with_mocked(expr: &mut Expr, name: &Ident, mocked: &Extracted) -> syn::Result<()> {
+    let path = match expr {
+        Expr::Path(path) => &mut path.path,
+        Expr::Call(call) => match call.func.as_mut() {
+            Expr::Path(p) => &mut p.path,
+            _ => return Ok(()),
+        },
+        Expr::Struct(s) => &mut s.path,
+        other => {
+            return Err(unsupported(
+                other,
+                format!("expected a variant of `{name}`, such as `{name}::Variant(value)`"),
+            ));
         }
+    };
 
-        _ => todo!("other exprs matter"),
-    }
+    let hint = format!("expected a variant of `{name}`, such as `{name}::Variant`");
+    let error = unsupported(&*path, hint);
+    let mocked_segment = path
+        .segments
+        .iter_mut()
+        .find(|path| path.ident == *name)
+        .ok_or(error)?;
+    mocked_segment.ident = mocked.name.clone();
+    Ok(())
 }
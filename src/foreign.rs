@@ -0,0 +1,204 @@
+use proc_macro2::{Delimiter, TokenStream, TokenTree};
+use quote::quote;
+use syn::{
+    Attribute, Generics, Ident, ImplItem, ImplItemFn, ItemImpl, ItemTrait, Path, Token, Visibility,
+    parse::{Parse, ParseStream},
+    parse_quote,
+};
+
+use crate::diagnostics::{Diagnostics, unsupported};
+use crate::extract::{ExtractName, prepare_mock_name};
+use crate::options::Options;
+use crate::pimpl::MockContext;
+use crate::record::{Tracking, generate_mock_state, instance_name};
+use crate::traits::mock_trait;
+
+/// The input of `mock_foreign!`: the options of the mocks, given as `#![mock(...)]`, followed by
+/// the restated API of the foreign types and traits.
+pub struct Foreign {
+    options: Options,
+    items: Vec<ForeignItem>,
+}
+
+enum ForeignItem {
+    /// `impl reqwest::Client { ... }` or `impl Trait for reqwest::Client { ... }`, of which the
+    /// functions do not need a body.
+    Impl(ItemImpl),
+    /// `trait std::io::Read { ... }`, which is parsed as a trait named after the last segment of
+    /// the path.
+    Trait(Path, ItemTrait),
+}
+
+impl Parse for Foreign {
+    fn parse(input: ParseStream<'_>) -> syn::Result<Self> {
+        let mut options = Options::default();
+        for attr in input.call(Attribute::parse_inner)? {
+            if !attr.path().is_ident("mock") {
+                return Err(unsupported(
+                    &attr,
+                    "expected the options of the mocks as `#![mock(...)]`",
+                ));
+            }
+            attr.parse_nested_meta(|meta| options.parse(&meta))?;
+        }
+
+        let mut items = vec![];
+        while !input.is_empty() {
+            items.push(input.parse()?);
+        }
+        Ok(Self { options, items })
+    }
+}
+
+impl Parse for ForeignItem {
+    fn parse(input: ParseStream<'_>) -> syn::Result<Self> {
+        let ahead = input.fork();
+        ahead.call(Attribute::parse_outer)?;
+        ahead.parse::<Visibility>()?;
+        ahead.parse::<Option<Token![unsafe]>>()?;
+        if ahead.peek(Token![impl]) {
+            return input.parse().map(Self::Impl);
+        }
+        if !ahead.peek(Token![trait]) {
+            return Err(input.error("expected an `impl` block or a trait of a foreign crate"));
+        }
+
+        let attrs = input.call(Attribute::parse_outer)?;
+        let vis: Visibility = input.parse()?;
+        let unsafety: Option<Token![unsafe]> = input.parse()?;
+        let trait_token: Token![trait] = input.parse()?;
+        let path = Path::parse_mod_style(input)?;
+        let ident = &path.segments.last().expect("paths have a segment").ident;
+        let rest = input.step(|cursor| {
+            let mut rest = *cursor;
+            let mut tokens = TokenStream::new();
+            while let Some((token, next)) = rest.token_tree() {
+                rest = next;
+                let is_body = matches!(
+                    &token,
+                    TokenTree::Group(group) if group.delimiter() == Delimiter::Brace
+                );
+                tokens.extend([token]);
+                if is_body {
+                    return Ok((tokens, rest));
+                }
+            }
+            Err(cursor.error("expected the body of the trait"))
+        })?;
+
+        let item = syn::parse2(quote!(#(#attrs)* #vis #unsafety #trait_token #ident #rest))?;
+        Ok(Self::Trait(path, item))
+    }
+}
+
+/// Generates the mocks of the foreign types and traits. A type gets a unit `<Name>Mock` with the
+/// methods of its `impl` blocks, and a trait gets a mock in the same way as with `#[mock]`.
+pub fn mock_foreign(foreign: Foreign) -> TokenStream {
+    let naming = foreign.options.naming();
+    if let Some(name) = naming.name() {
+        return unsupported(
+            name,
+            "`name` cannot be given to `mock_foreign!`, use `prefix` or `suffix` instead",
+        )
+        .to_compile_error();
+    }
+    if let Some(spy) = foreign.options.spy() {
+        return unsupported(spy, "`spy` can only be given to `#[mock_impl]`").to_compile_error();
+    }
+    if let Some(convert) = foreign.options.convert() {
+        return unsupported(convert, "`convert` can only be given to `#[mock]`").to_compile_error();
+    }
+
+    let mut diagnostics = Diagnostics::default();
+    let mut declared: Vec<Ident> = vec![];
+    let mocks: TokenStream = foreign
+        .items
+        .into_iter()
+        .map(|item| match item {
+            ForeignItem::Trait(path, mut item) => mock_trait(&mut item, Some(&path), naming).into(),
+            ForeignItem::Impl(mut imp) => {
+                let extracted = match imp.self_ty.extract_name() {
+                    Ok(extracted) => extracted,
+                    Err(error) => {
+                        diagnostics.push(error);
+                        return TokenStream::new();
+                    }
+                };
+                if extracted.generics.is_some() || !imp.generics.params.is_empty() {
+                    diagnostics.unsupported(&imp.self_ty, "generic foreign types cannot be mocked");
+                    return TokenStream::new();
+                }
+                let context = match MockContext::new(&imp.self_ty, naming) {
+                    Ok(context) => context,
+                    Err(error) => {
+                        diagnostics.push(error);
+                        return TokenStream::new();
+                    }
+                };
+
+                let mock_name = prepare_mock_name(&extracted, naming).name;
+                let declaration = (!declared.contains(&mock_name)).then(|| {
+                    declared.push(mock_name.clone());
+                    declare_mock(&mock_name)
+                });
+                fill_in_bodies(&mut imp);
+                let mocked = TokenStream::from(context.mock_impl(imp));
+
+                quote! {
+                    #declaration
+                    #mocked
+                }
+            }
+        })
+        .collect();
+
+    let errors = diagnostics.into_compile_errors();
+    let mocks = foreign.options.gate(mocks);
+    quote! {
+        #mocks
+        #errors
+    }
+}
+
+/// Declares the mock of a foreign type, which gets its methods from the `impl` blocks. It only
+/// holds the identifier of its instance.
+fn declare_mock(mock_name: &Ident) -> TokenStream {
+    let instance = instance_name(mock_name);
+    let tracking = Tracking::Owned(parse_quote!(0));
+    let state = generate_mock_state(
+        &parse_quote!(pub),
+        mock_name,
+        &Generics::default(),
+        &tracking,
+    );
+
+    quote! {
+        #[derive(Clone, Debug, Default)]
+        pub struct #mock_name(#[doc(hidden)] pub #instance);
+
+        impl #mock_name {
+            pub fn mock_new() -> Self {
+                Self::default()
+            }
+        }
+
+        #state
+    }
+}
+
+/// Gives an empty body to the functions restated without one, as in `pub fn get(&self) -> u8;`,
+/// which are otherwise parsed as verbatim tokens.
+fn fill_in_bodies(imp: &mut ItemImpl) {
+    for item in &mut imp.items {
+        let ImplItem::Verbatim(tokens) = item else {
+            continue;
+        };
+        let mut tokens: Vec<TokenTree> = tokens.clone().into_iter().collect();
+        if matches!(tokens.last(), Some(TokenTree::Punct(punct)) if punct.as_char() == ';') {
+            tokens.pop();
+        }
+        if let Ok(function) = syn::parse2::<ImplItemFn>(quote!(#(#tokens)* {})) {
+            *item = ImplItem::Fn(function);
+        }
+    }
+}
@@ -1,4 +1,3 @@
-#![feature(extend_one)]
 #![allow(clippy::module_name_repetitions)]
 #![deny(rust_2018_idioms)]
 #![deny(clippy::pedantic)]
@@ -16,8 +15,16 @@
 //! The [`macro@mock`] macro will generate mocks for the structs or enums it is defined on. This will
 //! create a new type that mirrors the type.
 //!
-//! The mocks generated are not test-gated at the moment. This may change in a future release. It
-//! is expected that the user wraps it in the `test` feature gate.
+//! The mocks are only generated under `#[cfg(test)]` by default, so they do not end up in normal
+//! builds. Every macro takes the same options to change this:
+//!
+//! - `cfg = "predicate"` generates the mocks under another `cfg`, such as
+//!   `#[mock(cfg = "any(test, feature = \"mocks\")")]` to ship them behind a cargo feature for
+//!   downstream crates,
+//! - `always` generates the mocks in every build.
+//!
+//! Outside of the `cfg`, `#[toffel]` keeps the original types of the fields and `#[mock_impl]` only
+//! keeps the original implementation.
 //!
 //! ```
 //! use mocktoffel::{toffel, mock};
@@ -39,24 +46,96 @@
 //!
 //!     
 //! ```
+use crate::diagnostics::Diagnostics;
+use crate::options::Options;
 use crate::pimpl::MockContext;
+use crate::record::Tracking;
+use foreign::Foreign;
 use proc_macro::TokenStream;
 use quote::quote;
-use syn::{Item, ItemImpl, parse_macro_input, parse_str};
+use syn::{ImplItem, Item, ItemEnum, ItemImpl, ItemStruct, parse_macro_input};
 use toffel::Toffelise;
 
 mod branch;
+mod builder;
+mod convert;
+mod diagnostics;
 mod extract;
+mod foreign;
+mod matchers;
+mod options;
 mod pimpl;
+mod record;
 mod toffel;
+mod traits;
 
 /// The toffel macro is the initiator which will replace the fields with their corresponding mock
 /// types. The fields need to be marked with the `#[mocked]` attribute. The fields without the
 /// attribute will be retained as original.
 ///
-/// The mocked fields need to have their mocks generated by using the [`macro@mock`] macro. Support
-/// for other types that do not have the [`macro@mock`] macro or belong to other crates will be added
-/// in a future release.
+/// The mocked fields need to have their mocks generated by using the [`macro@mock`] macro, or by
+/// [`mock_foreign!`] for the types and traits of other crates. As the mocks of `mock_foreign!` live
+/// in the crate using them, fields of foreign types are marked with `#[mocked(foreign)]`, which
+/// looks the mock up in the current scope so that `reqwest::Client` becomes `ClientMock`. A plain
+/// `#[mocked]` cannot tell the types of other crates apart and looks the mock up next to the type,
+/// as `reqwest::ClientMock`, which only exists when that crate mocks the type itself.
+///
+/// The fields are only replaced under `#[cfg(test)]`, or the `cfg` given through the options of the
+/// macro. Other builds use the struct as it is written. Both versions keep the name, visibility,
+/// attributes and generics of the original, so the code using the struct compiles against either
+/// of them without changes.
+///
+/// Trait objects are replaced with the mock of their trait, so a `#[mocked]` field of type
+/// `Box<dyn Clock + Send>` becomes a `Box<ClockMock>` when the trait has the [`macro@mock`] macro.
+/// Auto traits like `Send` and `Sync` are checked to hold for the mock.
+///
+/// Mocking a combination of traits, such as `impl Read + Write`, is out of scope: the macro only
+/// sees the names of the traits on the field and not their methods, so it cannot generate one mock
+/// implementing all of them. Such fields are reported, and are replaced through
+/// `#[mocked(with = Type)]` with a type implementing every trait.
+///
+/// Associated types are resolved through the mock of their trait, so `<B as Backend>::Conn`
+/// becomes `<BackendMock as Backend>::Conn` and takes the type set on `BackendMock`.
+///
+/// Arrays, slices and the containers and wrappers of the standard library keep their type and
+/// have their contents mocked, so `Vec<Worker>` becomes `Vec<WorkerMock>`, `HashMap<u32, Worker>`
+/// becomes `HashMap<u32, WorkerMock>` and `Result<Worker, Error>` becomes
+/// `Result<WorkerMock, Error>`. For other generic types, only the trait objects in their arguments
+/// are mocked.
+///
+/// References and raw pointers keep their lifetime, mutability and kind, so `&'a mut Db` becomes
+/// `&'a mut DbMock` and `*const Handle` becomes `*const HandleMock`.
+///
+/// Tuples keep their shape and have their elements mocked, so `(Db, Cache)` becomes
+/// `(DbMock, CacheMock)`. The elements to mock can be picked by their position with
+/// `#[mocked(0, 2)]`, leaving the other elements as they are.
+///
+/// Function pointers and boxed closures (`Box<dyn Fn(u32) -> bool>`) are replaced with a generated
+/// mock named after the type and the field, such as `FooCallbackMock` for the `callback` field of
+/// `Foo`, which is as visible as the field. The mock can be called like the original function and
+/// records its calls, which can be checked with `expect_call`. Its return value is configured
+/// through `returning_call` and `return_const_call`.
+///
+/// The mocks are looked up as `<Name>Mock` at the path of the original type. Mocks named with a
+/// `prefix` or `suffix` are found by giving the same option to the macro, as in
+/// `#[toffel(prefix = "Mock")]` for `MockFoo`. A field can be replaced with any type through
+/// `#[mocked(with = FakeFoo)]`.
+///
+/// ```rust
+/// use mocktoffel::toffel;
+///
+/// #[toffel(always)]
+/// struct Foo {
+///     #[mocked]
+///     callback: fn(u32) -> bool,
+/// }
+///
+/// let foo = Foo { callback: FooCallbackMock::mock_new() };
+/// foo.callback.return_const_call(true);
+///
+/// assert!((foo.callback)(1));
+/// let _ = foo.callback.expect_call().times(1);
+/// ```
 ///
 /// ```rust
 /// use mocktoffel::{toffel, mock};
@@ -74,29 +153,99 @@ mod toffel;
 ///  
 #[proc_macro_attribute]
 pub fn toffel(tokens: TokenStream, input: TokenStream) -> TokenStream {
+    let mut options = Options::default();
+    let parser = syn::meta::parser(|meta| options.parse(&meta));
+    parse_macro_input!(tokens with parser);
     let tokens = parse_macro_input!(input as Item);
-
-    match tokens {
-        Item::Struct(s) => s.replace_mocks(),
-        Item::Enum(e) => e.replace_mocks(),
-        _ => todo!(),
+    if let Some(name) = options.naming().name() {
+        return TokenStream::from(
+            diagnostics::unsupported(
+                name,
+                "`name` can only be given to `#[mock]` and `#[mock_impl]`, use \
+                 `#[mocked(with = Type)]` to pick the type of a field",
+            )
+            .to_compile_error(),
+        );
+    }
+    if let Some(spy) = options.spy() {
+        return TokenStream::from(
+            diagnostics::unsupported(spy, "`spy` can only be given to `#[mock_impl]`")
+                .to_compile_error(),
+        );
     }
+    if let Some(convert) = options.convert() {
+        return TokenStream::from(
+            diagnostics::unsupported(convert, "`convert` can only be given to `#[mock]`")
+                .to_compile_error(),
+        );
+    }
+    let original = toffel::without_mocked_attributes(&tokens);
+
+    let naming = options.naming();
+    let mocked = match tokens {
+        Item::Struct(s) => s.replace_mocks(naming),
+        Item::Enum(e) => e.replace_mocks(naming),
+        Item::Union(u) => u.replace_mocks(naming),
+        other => {
+            return TokenStream::from(
+                diagnostics::unsupported(
+                    other,
+                    "`#[toffel]` can only be used on structs, enums and unions",
+                )
+                .to_compile_error(),
+            );
+        }
+    };
+
+    TokenStream::from(options.switch(&original, mocked.into()))
 }
 
-/// The `mock` macro will generate the corresponding mock for the struct or enum. In future this
-/// will support mocking other types as well. The macro generates a new type named `<Name>Mock` that will match the
-/// existing type with the same fields. The type is not test-gated at the moment, so the user is
-/// expected to wrap the macro call in a feature gate.
+/// The `mock` macro will generate the corresponding mock for the struct, enum, union or trait. The
+/// macro generates a new type named `<Name>Mock` that will match the existing type with the same
+/// fields. The mock is only generated under `#[cfg(test)]` unless
+/// another `cfg` is given with `#[mock(cfg = "predicate")]`, or `#[mock(always)]` is used. The
+/// examples below use `always` as doctests are not built with `cfg(test)`.
+///
+/// The mock can be given another name with `#[mock(name = "FakeFoo")]`, or be named with a
+/// `prefix` and `suffix` around the original name instead of the `Mock` suffix, such as
+/// `#[mock(prefix = "Mock")]` for `MockFoo`. The [`macro@mock_impl`] of the type needs the same
+/// option to find the mock.
 ///
 /// The macro also creates a function on the type that crates a mocked object. This ensures that a
 /// type can be created on types that do not implement `Default`. The method will use the custom
 /// value of the field provided by using the `#[mocked_with(value)]` attribute. If this attribute
 /// is not present, the default value of the type will be used.
 ///
-/// For enums, the `#[mocked_with(value)]` needs to be placed on at most one variant as it will be
-/// used as the default variant to construct the enum. The default value of the data should be
-/// passed as a parameter to the attribute. If the attribute isn't specified, then the
-/// default variant of the enum
+/// For enums, the `#[mocked_with = value]` needs to be placed on at most one variant as it will be
+/// used as the default variant to construct the enum. A plain `#[mocked_with]` on a variant builds
+/// it from the default values of its fields, and `#[mocked_with(value)]` on the fields of a tuple
+/// or struct-like variant builds that variant with the given values. If the attribute isn't
+/// specified, then the default variant of the enum is used.
+///
+/// The mocks of structs with named fields hold a hidden field telling their instances apart, so
+/// they are built through `mock_new` or with the rest of the fields taken from it, as in
+/// `FooMock { foo: 1, ..FooMock::mock_new() }`. Unit structs get a unit mock, such as
+/// `SystemClockMock` for `struct SystemClock;`. For unions,
+/// `mock_new` initialises the field marked with `#[mocked_with(value)]`, or the first field with
+/// its default value.
+///
+/// To change a few fields of a mock, `mock_with` takes a closure that receives the mock built by
+/// `mock_new`. Structs also get a `builder` with a function for each field, named after the field
+/// or `field_<position>` for tuple structs, so `ConfigMock::builder().port(9000).build()` keeps
+/// the other values of `mock_new`. Each enum variant with fields gets such a builder through
+/// `<variant>_builder` in snake case, such as `http_error_builder` for `HTTPError`, which starts
+/// from the values given to its fields with `#[mocked_with(value)]` and the default values of the
+/// others. The fields marked with `#[mocked]` for a [`macro@toffel`] below the mock are set to
+/// their mock, as in `ServiceMock::builder().config(ConfigMock::mock_new())`.
+///
+/// With `#[mock(convert)]`, structs and enums also get `From` in both directions between the
+/// type and its mock, such as `FooMock::from(foo)` and `Foo::from(mock)`. The fields are moved
+/// over, so the type cannot implement `Drop`. The mock holds the mocks of the fields marked with
+/// `#[mocked]` for a [`macro@toffel`] below it wherever the mock exists, even when the `cfg` of
+/// the `toffel` leaves the original fields in place. Those fields are converted with `Into`, and
+/// the containers of the standard library item by item, so that a `Vec<Foo>` is converted to and
+/// from a `Vec<FooMock>`. An `Rc` or an `Arc` gets a new pointer, cloning its item when it is
+/// still shared, and only a pinned `Box` can be converted, while a `Weak` is rejected.
 ///
 /// To replace the original struct with the mocked struct in implementations and trait implements,
 /// add the [`macro@mock_impl`] proc-macro to the `impl` and trait implementations where the type is
@@ -117,59 +266,365 @@ pub fn toffel(tokens: TokenStream, input: TokenStream) -> TokenStream {
 ///     
 /// ```
 ///
-#[allow(clippy::missing_panics_doc)]
+/// When used on a trait, the macro generates a `<Name>Mock` type that implements the trait. All the
+/// methods, including the ones with a default implementation, are mocked in the same way as with
+/// [`macro@mock_impl`]. Associated types and constants use the value given through
+/// `#[mocked_with(value)]`, or the default in the trait if there is one. Associated types without
+/// either are set to `()`.
+///
+/// ```rust
+/// use mocktoffel::mock;
+///
+/// #[mock(always)]
+/// pub trait Storage {
+///     #[mocked_with(String)]
+///     type Key;
+///
+///     fn get(&self, key: &str) -> Option<Vec<u8>>;
+/// }
+///
+/// let storage = StorageMock::mock_new();
+/// storage.return_const_get(Some(vec![1]));
+///
+/// assert_eq!(storage.get("key"), Some(vec![1]));
+/// let _ = storage.expect_get().times(1);
+/// ```
+///
 #[proc_macro_attribute]
 pub fn mock(tokens: TokenStream, input: TokenStream) -> TokenStream {
+    let mut options = Options::default();
+    let parser = syn::meta::parser(|meta| options.parse(&meta));
+    parse_macro_input!(tokens with parser);
     let mut tokens = parse_macro_input!(input as Item);
+    if let Some(spy) = options.spy() {
+        return TokenStream::from(
+            diagnostics::unsupported(spy, "`spy` can only be given to `#[mock_impl]`")
+                .to_compile_error(),
+        );
+    }
+    if let Item::Trait(ref mut t) = tokens {
+        if let Some(convert) = options.convert() {
+            return TokenStream::from(
+                diagnostics::unsupported(
+                    convert,
+                    "`convert` can only be given to structs and enums",
+                )
+                .to_compile_error(),
+            );
+        }
+        let mock = options.gate(traits::mock_trait(t, None, options.naming()).into());
+        return TokenStream::from(quote! {
+            #tokens
+            #mock
+        });
+    }
+
     let mut mock = tokens.clone();
-    let fields = match mock {
+    let (fields, state) = match mock {
         Item::Struct(ref mut s) => {
-            let name = format!("{}Mock", s.ident);
-            s.ident = parse_str(name.as_str()).unwrap();
-            extract::parse_fields_and_generate_for_values(s)
+            toffel::ungate(&mut s.attrs);
+            s.ident = options.naming().mock_name(&s.ident);
+            let tracking = record::add_instance_field(&mut s.fields, &s.attrs, &s.ident);
+            (
+                extract::parse_fields_and_generate_for_values(s),
+                record::generate_mock_state(&s.vis, &s.ident, &s.generics, &tracking),
+            )
+        }
+        Item::Union(ref mut u) => {
+            toffel::ungate(&mut u.attrs);
+            u.ident = options.naming().mock_name(&u.ident);
+            (
+                extract::parse_fields_and_generate_for_union(u),
+                record::generate_mock_state(&u.vis, &u.ident, &u.generics, &Tracking::Shared),
+            )
         }
         Item::Enum(ref mut e) => {
-            let f = extract::parse_fields_and_generate_variant(e);
-            let name = format!("{}Mock", e.ident);
-            e.ident = parse_str(name.as_str()).unwrap();
-            f
+            toffel::ungate(&mut e.attrs);
+            let f = extract::parse_fields_and_generate_variant(e, options.naming());
+            e.ident = options.naming().mock_name(&e.ident);
+            (
+                f,
+                record::generate_mock_state(&e.vis, &e.ident, &e.generics, &Tracking::Shared),
+            )
+        }
+        other => {
+            return TokenStream::from(
+                diagnostics::unsupported(
+                    other,
+                    "`#[mock]` can only be used on structs, enums, unions and traits",
+                )
+                .to_compile_error(),
+            );
         }
-        _ => todo!(),
     };
 
-    extract::clean_out_attributes(&mut tokens);
-    extract::clean_out_attributes(&mut mock);
-    let mut original_and_mocked_struct = TokenStream::from(quote! {
-        #tokens
+    let conversions = match (options.convert(), &mock) {
+        (Some(_), Item::Struct(ItemStruct { ident, .. }) | Item::Enum(ItemEnum { ident, .. })) => {
+            convert::generate_conversions(&tokens, ident)
+        }
+        (Some(convert), _) => {
+            diagnostics::unsupported(convert, "`convert` can only be given to structs and enums")
+                .to_compile_error()
+        }
+        (None, _) => proc_macro2::TokenStream::new(),
+    };
+    let mut diagnostics = Diagnostics::default();
+    extract::clean_out_attributes(&mut tokens, &mut diagnostics);
+    extract::clean_out_attributes(&mut mock, &mut diagnostics);
+    let errors = diagnostics.into_compile_errors();
+    let fields = proc_macro2::TokenStream::from(fields);
+    let mocked = options.gate(quote! {
         #mock
+        #state
+        #fields
+        #conversions
     });
 
-    original_and_mocked_struct.extend_one(fields);
-    original_and_mocked_struct
+    TokenStream::from(quote! {
+        #tokens
+        #mocked
+        #errors
+    })
 }
 
 /// A helper macro that substitutes the original type with the mocked type on implementations and
-/// trait implementations. The macro will take care of the occurrence of the type arguments and
-/// return types.
+/// trait implementations. Every occurrence of the original type in the signatures, where clauses
+/// and associated types is replaced, so that `&Foo`, `Vec<Foo>` and `Result<Foo, Error>` become
+/// `&FooMock`, `Vec<FooMock>` and `Result<FooMock, Error>`.
+///
+/// The original implementation is kept as it is, and the implementation for the mock is only
+/// generated under `#[cfg(test)]`, or the `cfg` given through the options of the macro.
+///
+/// Every call to a mocked method is recorded along with its arguments, in the log of the mock it
+/// was made on, or of the mock type for functions without a receiver. The logs of the mocks are
+/// shared by all threads, so the calls made through an `Arc` or on a multi-threaded runtime are
+/// recorded as well, while the log of the mock type is kept per thread, so that the tests running
+/// in parallel do not see each other's calls. For each method, the macro generates an
+/// `expect_<method>` function on the mock that can be used to assert how often the method was
+/// called. The functions of trait implementations are qualified with the trait and its generic
+/// arguments, as in `expect_display_fmt` for the `fmt` method of `impl Display` or
+/// `expect_from_u8_from` for `impl From<u8>`, so that they are kept apart from an inherent method
+/// of the same name and from the other implementations of the trait. The arguments are formatted
+/// with `Debug` when the type implements it. Through `with`, only the calls of which the arguments
+/// match the matchers of [`matchers!`] are counted.
+///
+/// Besides `times`, the number of calls is checked with `at_least`, `at_most` and `never`, against
+/// the calls made so far. An expectation made `deferred` is checked against the calls made after
+/// it instead, once `checkpoint` is called on the mock or the mock is dropped. It expects at least
+/// one call unless told otherwise. Deferred expectations belong to the mock they were configured
+/// on, so dropping a clone or another mock of the type leaves them be, and are ordered across
+/// mocks by adding them to a `matchers::Sequence` through `in_sequence`. Mocks that are `Copy`,
+/// and the mocks of enums, unions, unit and tuple structs, which share their log with every mock
+/// of their type, are not dropped as an instance of their own, so their expectations cannot be
+/// deferred and have no `checkpoint`. The deferred expectations of functions without a receiver
+/// are only verified through `checkpoint`. When they are not satisfied, the panic lists
+/// every method of the implementation with its number of calls. Mocks dropped while the test is
+/// already panicking leave their expectations unchecked.
+///
+/// The mocked methods return the default value of their return type, or the mock of the original
+/// type if that is what they return. A different value can be set for a method by using the
+/// `#[mocked_with(value)]` attribute on it. Tests can override the value through the generated
+/// `returning_<method>` function, which takes a closure receiving the arguments as a tuple, or
+/// `return_const_<method>` for a fixed value. The value is overridden for the mock the function is
+/// called on, like the calls are recorded, whichever thread the mock is called from, so the
+/// closure and the value have to be `Send`. This is not available for methods that are generic or
+/// return references.
+///
+/// With `#[mock_impl(spy)]`, the mocked methods run their original body on the fields of the mock
+/// unless a value is set through `#[mocked_with(value)]` or `returning_<method>`, so that only the
+/// methods that need it are faked. Their calls are recorded in the same way. The mocks they build,
+/// as in `Self { .. }` or `module::Foo { .. }`, get a new instance of their own, and those built
+/// from a single field, as are unions, start from `mock_with`.
+///
+/// The methods keep their signature and attributes, including `async`, `unsafe` and their
+/// generics, as does the implementation itself, except for `const`, as calls cannot be recorded
+/// during constant evaluation. The `const` methods of the mock are called at runtime instead.
+///
+/// Associated constants, types and macros are carried over to the implementation for the mock as
+/// they are written. The value of a constant can be replaced for the mock with
+/// `#[mocked_with(value)]`.
 ///
 /// ```rust
 /// use mocktoffel::{mock, mock_impl};
-/// #[mock]
+/// #[mock(always)]
 /// pub struct Foo {
 ///     foo: String
 /// }
 ///
-/// #[mock_impl]
+/// #[mock_impl(always)]
 /// impl Foo {
 ///     pub fn bar(f: Foo) {}
+///
+///     pub fn baz(&self, value: u32) {}
+///
+///     #[mocked_with(String::from("qux"))]
+///     pub fn qux(&self) -> String {
+///         self.foo.clone()
+///     }
 /// }
+///
+/// let foo = FooMock::mock_new();
+/// foo.baz(1);
+/// foo.baz(2);
+///
+/// let _ = foo.expect_baz().times(2);
+/// assert_eq!(foo.qux(), "qux");
+///
+/// let _ = foo.expect_baz().deferred().at_most(1);
+/// foo.baz(3);
+/// foo.checkpoint();
+///
+/// foo.return_const_qux("quux");
+/// assert_eq!(foo.qux(), "quux");
 /// ```
 ///
 #[allow(clippy::redundant_clone)]
 #[proc_macro_attribute]
 pub fn mock_impl(tokens: TokenStream, input: TokenStream) -> TokenStream {
+    let mut options = Options::default();
+    let parser = syn::meta::parser(|meta| options.parse(&meta));
+    parse_macro_input!(tokens with parser);
     let tokens = parse_macro_input!(input as ItemImpl);
-    let original_type = tokens.self_ty.clone();
-    let context = MockContext::new(original_type);
-    context.mock_impl(tokens)
+    if let Some(convert) = options.convert() {
+        return TokenStream::from(
+            diagnostics::unsupported(convert, "`convert` can only be given to `#[mock]`")
+                .to_compile_error(),
+        );
+    }
+    let context = match MockContext::new(&tokens.self_ty, options.naming()) {
+        Ok(context) => context.spying(options.spy().is_some()),
+        Err(error) => return TokenStream::from(error.to_compile_error()),
+    };
+
+    let mut original = tokens.clone();
+    for item in &mut original.items {
+        match item {
+            ImplItem::Fn(f) => {
+                extract::take_mocked_with(&mut f.attrs);
+            }
+            ImplItem::Const(c) => {
+                extract::take_mocked_with(&mut c.attrs);
+            }
+            _ => {}
+        }
+    }
+    let mocked = options.gate(context.mock_impl(tokens).into());
+
+    TokenStream::from(quote! {
+        #original
+        #mocked
+    })
+}
+
+/// Generates mocks for types and traits of other crates from their restated API. Types are
+/// restated through `impl` blocks, of which the functions do not need a body, and get a
+/// `<Name>Mock` without fields of its own, built with `mock_new`, with the mocked methods. Traits
+/// are restated with their full path and get a mock in the same way as with [`macro@mock`].
+///
+/// The options of the mocks are given as `#![mock(...)]` at the start, in the same way as for
+/// [`macro@mock`]. The mocks are named after the last segment of the path of the original, so they
+/// are used in [`macro@toffel`] through `#[mocked(foreign)]` fields, which look the mock up in the
+/// current scope. The `foreign` marker is required: a plain `#[mocked]` field of type
+/// `http::Client` looks for `http::ClientMock` next to the type instead, which does not exist.
+///
+/// ```rust
+/// use mocktoffel::{mock_foreign, toffel};
+///
+/// mod http {
+///     pub struct Client;
+///
+///     impl Client {
+///         pub fn get(&self, url: &str) -> String {
+///             format!("GET {url}")
+///         }
+///     }
+/// }
+///
+/// mock_foreign! {
+///     #![mock(always)]
+///
+///     impl http::Client {
+///         pub fn get(&self, url: &str) -> String;
+///     }
+///
+///     trait std::io::Write {
+///         #[mocked_with(Ok(0))]
+///         fn write(&mut self, buf: &[u8]) -> std::io::Result<usize>;
+///
+///         #[mocked_with(Ok(()))]
+///         fn flush(&mut self) -> std::io::Result<()>;
+///     }
+/// }
+///
+/// #[toffel(always)]
+/// struct Fetcher {
+///     #[mocked(foreign)]
+///     client: http::Client,
+/// }
+///
+/// let fetcher = Fetcher { client: ClientMock::mock_new() };
+/// fetcher.client.return_const_get("mocked");
+///
+/// assert_eq!(fetcher.client.get("https://example.com"), "mocked");
+/// let _ = fetcher.client.expect_get().times(1);
+/// ```
+#[proc_macro]
+pub fn mock_foreign(input: TokenStream) -> TokenStream {
+    let foreign = parse_macro_input!(input as Foreign);
+    TokenStream::from(foreign::mock_foreign(foreign))
+}
+
+/// Generates the `matchers` module, with which the calls recorded by mocks are narrowed down to
+/// those with matching arguments through the `with` function of their expectations. It is
+/// invoked once, for instance next to the tests, and its matchers are used for every mock.
+///
+/// The matchers are `eq`, `ne`, `any`, `gt`, `ge`, `lt`, `le`, `contains`, `starts_with`,
+/// `predicate` for a closure, and the `and`, `or` and `not` combinators. They are given one per
+/// argument through `args`, or `arg` for methods with a single argument. Borrowed arguments are
+/// matched as their owned counterpart, so an `&str` is matched as a `String`, and arguments that
+/// cannot be cloned, sent to other threads or may borrow, such as an `Option<&str>` or a
+/// `Cow<str>`, are only matched by `any`. The matchers are sent to other threads along with the
+/// expectations they were given to, so they have to be `Send`. Matching is not available for the
+/// methods that do not support `returning_<method>`.
+///
+/// The calls that do not match are listed when an assertion such as `times` fails, along with the
+/// argument that did not match, formatted with `Debug` when available, and what was expected.
+///
+/// The module also holds the `Sequence` that orders the deferred expectations of any mocks.
+///
+/// ```rust
+/// use mocktoffel::{matchers, mock, mock_impl};
+///
+/// matchers!();
+/// use matchers::{any, arg, args, gt, starts_with};
+///
+/// #[mock(always)]
+/// pub struct Client {}
+///
+/// #[mock_impl(always)]
+/// impl Client {
+///     pub fn get(&self, url: &str, retries: u8) {}
+///
+///     pub fn wait(&self, seconds: u64) {}
+/// }
+///
+/// let client = ClientMock::mock_new();
+/// client.get("https://example.com", 3);
+/// client.get("http://example.com", 0);
+/// client.wait(10);
+///
+/// let _ = client.expect_get().with(args((starts_with("https"), any()))).times(1);
+/// let _ = client.expect_get().with(args((any(), gt(0)))).times(1);
+/// let _ = client.expect_wait().with(arg(gt(5))).times(1);
+/// ```
+#[proc_macro]
+pub fn matchers(input: TokenStream) -> TokenStream {
+    let input = proc_macro2::TokenStream::from(input);
+    if !input.is_empty() {
+        return TokenStream::from(
+            diagnostics::unsupported(&input, "`matchers!` does not take any arguments")
+                .to_compile_error(),
+        );
+    }
+    TokenStream::from(matchers::generate_matchers())
 }
@@ -0,0 +1,411 @@
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote};
+use syn::Index;
+
+/// The largest number of arguments that `args` can match.
+const MAX_ARGUMENTS: usize = 8;
+
+/// Generates the `matchers` module, which holds the matchers given to the `with` function of the
+/// expectations. As a proc macro crate cannot export them, they are generated in the crate using
+/// them.
+pub fn generate_matchers() -> TokenStream {
+    let arguments = (1..=MAX_ARGUMENTS).map(arguments_impl);
+    let value_matchers = value_matchers();
+    let sequence_matchers = sequence_matchers();
+    let combinators = combinators();
+    let sequence = sequence();
+
+    quote! {
+        /// Matchers for the arguments of the calls recorded by mocks, passed to the `with`
+        /// function of their expectations through [`args`] or [`arg`], and the [`Sequence`]
+        /// that orders deferred expectations.
+        #[allow(dead_code)]
+        pub mod matchers {
+            use ::std::any::Any;
+            use ::std::borrow::ToOwned;
+            use ::std::fmt::Debug;
+            use ::std::marker::PhantomData;
+            use ::std::option::Option;
+            use ::std::string::String;
+            use ::std::vec::Vec;
+
+            /// A check on a single argument of type `T`. Arguments are recorded as an owned copy,
+            /// so an `&str` argument is matched as a `String`, and arguments that cannot be
+            /// cloned or sent to other threads are not recorded at all.
+            pub trait Matcher<T: ?Sized> {
+                /// Whether the recorded argument matches, given as `None` if it was not recorded.
+                fn matches(&self, value: Option<&dyn Any>) -> bool;
+
+                /// What the matcher expects, shown when an argument does not match.
+                fn describe(&self) -> String;
+            }
+
+            /// The owned copy of an argument of type `T`, if it was recorded.
+            pub fn recorded<T: ToOwned + ?Sized>(value: Option<&dyn Any>) -> Option<&T::Owned>
+            where
+                T::Owned: 'static,
+            {
+                value.and_then(|value| value.downcast_ref::<T::Owned>())
+            }
+
+            /// A check on all the arguments of a call, implemented for tuples with one matcher
+            /// per argument.
+            pub trait Arguments<S> {
+                /// The position of the first argument that does not match along with what was
+                /// expected of it.
+                fn mismatch(&self, values: &[Option<&dyn Any>]) -> Option<(usize, String)>;
+            }
+
+            /// Matches each argument of a call with the matcher at the same position.
+            pub fn args<S, A: Arguments<S>>(
+                matchers: A,
+            ) -> impl Fn(PhantomData<S>, &[Option<&dyn Any>]) -> Option<(usize, String)> {
+                move |_, values| matchers.mismatch(values)
+            }
+
+            /// Matches the single argument of a call.
+            pub fn arg<S, M>(
+                matcher: M,
+            ) -> impl Fn(PhantomData<S>, &[Option<&dyn Any>]) -> Option<(usize, String)>
+            where
+                (M,): Arguments<S>,
+            {
+                args((matcher,))
+            }
+
+            #(#arguments)*
+
+            #value_matchers
+
+            #sequence_matchers
+
+            #combinators
+
+            #sequence
+        }
+    }
+}
+
+/// The matchers comparing arguments with a value.
+fn value_matchers() -> TokenStream {
+    quote! {
+        pub struct Equals<V>(V);
+
+        /// Matches arguments equal to `value`.
+        pub fn eq<V>(value: V) -> Equals<V> {
+            Equals(value)
+        }
+
+        impl<T: ToOwned + ?Sized, V: Debug> Matcher<T> for Equals<V>
+        where
+            T::Owned: PartialEq<V> + 'static,
+        {
+            fn matches(&self, value: Option<&dyn Any>) -> bool {
+                recorded::<T>(value).is_some_and(|value| *value == self.0)
+            }
+
+            fn describe(&self) -> String {
+                format!("{:?}", self.0)
+            }
+        }
+
+        pub struct NotEquals<V>(V);
+
+        /// Matches arguments that are not equal to `value`.
+        pub fn ne<V>(value: V) -> NotEquals<V> {
+            NotEquals(value)
+        }
+
+        impl<T: ToOwned + ?Sized, V: Debug> Matcher<T> for NotEquals<V>
+        where
+            T::Owned: PartialEq<V> + 'static,
+        {
+            fn matches(&self, value: Option<&dyn Any>) -> bool {
+                recorded::<T>(value).is_some_and(|value| *value != self.0)
+            }
+
+            fn describe(&self) -> String {
+                format!("anything but {:?}", self.0)
+            }
+        }
+
+        pub struct Anything;
+
+        /// Matches every argument, including those that could not be recorded.
+        pub fn any() -> Anything {
+            Anything
+        }
+
+        impl<T: ?Sized> Matcher<T> for Anything {
+            fn matches(&self, _: Option<&dyn Any>) -> bool {
+                true
+            }
+
+            fn describe(&self) -> String {
+                String::from("anything")
+            }
+        }
+
+        pub struct Compare<V> {
+            value: V,
+            accepted: &'static [::std::cmp::Ordering],
+            description: &'static str,
+        }
+
+        /// Matches arguments greater than `value`.
+        pub fn gt<V>(value: V) -> Compare<V> {
+            Compare { value, accepted: &[::std::cmp::Ordering::Greater], description: ">" }
+        }
+
+        /// Matches arguments greater than or equal to `value`.
+        pub fn ge<V>(value: V) -> Compare<V> {
+            Compare {
+                value,
+                accepted: &[::std::cmp::Ordering::Greater, ::std::cmp::Ordering::Equal],
+                description: ">=",
+            }
+        }
+
+        /// Matches arguments less than `value`.
+        pub fn lt<V>(value: V) -> Compare<V> {
+            Compare { value, accepted: &[::std::cmp::Ordering::Less], description: "<" }
+        }
+
+        /// Matches arguments less than or equal to `value`.
+        pub fn le<V>(value: V) -> Compare<V> {
+            Compare {
+                value,
+                accepted: &[::std::cmp::Ordering::Less, ::std::cmp::Ordering::Equal],
+                description: "<=",
+            }
+        }
+
+        impl<T: ToOwned + ?Sized, V: Debug> Matcher<T> for Compare<V>
+        where
+            T::Owned: PartialOrd<V> + 'static,
+        {
+            fn matches(&self, value: Option<&dyn Any>) -> bool {
+                recorded::<T>(value)
+                    .and_then(|value| value.partial_cmp(&self.value))
+                    .is_some_and(|ordering| self.accepted.contains(&ordering))
+            }
+
+            fn describe(&self) -> String {
+                format!("{} {:?}", self.description, self.value)
+            }
+        }
+    }
+}
+
+/// The matchers looking into strings and vectors.
+fn sequence_matchers() -> TokenStream {
+    quote! {
+        /// The arguments that `contains` and `starts_with` can look into.
+        pub trait Searchable<V: ?Sized> {
+            fn contains_value(&self, value: &V) -> bool;
+
+            fn starts_with_value(&self, value: &V) -> bool;
+        }
+
+        impl<V: AsRef<str> + ?Sized> Searchable<V> for String {
+            fn contains_value(&self, value: &V) -> bool {
+                self.contains(value.as_ref())
+            }
+
+            fn starts_with_value(&self, value: &V) -> bool {
+                self.starts_with(value.as_ref())
+            }
+        }
+
+        impl<E: PartialEq, V: AsRef<[E]> + ?Sized> Searchable<V> for Vec<E> {
+            fn contains_value(&self, value: &V) -> bool {
+                let value = value.as_ref();
+                value.is_empty() || self.windows(value.len()).any(|window| window == value)
+            }
+
+            fn starts_with_value(&self, value: &V) -> bool {
+                self.starts_with(value.as_ref())
+            }
+        }
+
+        pub struct Contains<V>(V);
+
+        /// Matches strings containing the substring `value`, or vectors containing the
+        /// elements of `value` in a row.
+        pub fn contains<V>(value: V) -> Contains<V> {
+            Contains(value)
+        }
+
+        impl<T: ToOwned + ?Sized, V: Debug> Matcher<T> for Contains<V>
+        where
+            T::Owned: Searchable<V> + 'static,
+        {
+            fn matches(&self, value: Option<&dyn Any>) -> bool {
+                recorded::<T>(value).is_some_and(|value| value.contains_value(&self.0))
+            }
+
+            fn describe(&self) -> String {
+                format!("something containing {:?}", self.0)
+            }
+        }
+
+        pub struct StartsWith<V>(V);
+
+        /// Matches strings or vectors that start with `value`.
+        pub fn starts_with<V>(value: V) -> StartsWith<V> {
+            StartsWith(value)
+        }
+
+        impl<T: ToOwned + ?Sized, V: Debug> Matcher<T> for StartsWith<V>
+        where
+            T::Owned: Searchable<V> + 'static,
+        {
+            fn matches(&self, value: Option<&dyn Any>) -> bool {
+                recorded::<T>(value).is_some_and(|value| value.starts_with_value(&self.0))
+            }
+
+            fn describe(&self) -> String {
+                format!("something starting with {:?}", self.0)
+            }
+        }
+    }
+}
+
+/// The matchers taking a closure or other matchers.
+fn combinators() -> TokenStream {
+    quote! {
+        pub struct Predicate<F>(F);
+
+        /// Matches the arguments for which the closure returns `true`, which receives the
+        /// owned copy of the argument.
+        pub fn predicate<F>(predicate: F) -> Predicate<F> {
+            Predicate(predicate)
+        }
+
+        impl<T: ToOwned + ?Sized, F: Fn(&T::Owned) -> bool> Matcher<T> for Predicate<F>
+        where
+            T::Owned: 'static,
+        {
+            fn matches(&self, value: Option<&dyn Any>) -> bool {
+                recorded::<T>(value).is_some_and(|value| (self.0)(value))
+            }
+
+            fn describe(&self) -> String {
+                String::from("a value matching the predicate")
+            }
+        }
+
+        pub struct And<A, B>(A, B);
+
+        /// Matches the arguments that match both `a` and `b`.
+        pub fn and<A, B>(a: A, b: B) -> And<A, B> {
+            And(a, b)
+        }
+
+        impl<T: ?Sized, A: Matcher<T>, B: Matcher<T>> Matcher<T> for And<A, B> {
+            fn matches(&self, value: Option<&dyn Any>) -> bool {
+                self.0.matches(value) && self.1.matches(value)
+            }
+
+            fn describe(&self) -> String {
+                format!("({} and {})", self.0.describe(), self.1.describe())
+            }
+        }
+
+        pub struct Or<A, B>(A, B);
+
+        /// Matches the arguments that match `a`, `b` or both.
+        pub fn or<A, B>(a: A, b: B) -> Or<A, B> {
+            Or(a, b)
+        }
+
+        impl<T: ?Sized, A: Matcher<T>, B: Matcher<T>> Matcher<T> for Or<A, B> {
+            fn matches(&self, value: Option<&dyn Any>) -> bool {
+                self.0.matches(value) || self.1.matches(value)
+            }
+
+            fn describe(&self) -> String {
+                format!("({} or {})", self.0.describe(), self.1.describe())
+            }
+        }
+
+        pub struct Not<M>(M);
+
+        /// Matches the arguments that do not match `matcher`.
+        pub fn not<M>(matcher: M) -> Not<M> {
+            Not(matcher)
+        }
+
+        impl<T: ?Sized, M: Matcher<T>> Matcher<T> for Not<M> {
+            fn matches(&self, value: Option<&dyn Any>) -> bool {
+                !self.0.matches(value)
+            }
+
+            fn describe(&self) -> String {
+                format!("not {}", self.0.describe())
+            }
+        }
+    }
+}
+
+/// The order in which the calls of deferred expectations have to be made, which is shared by the
+/// mocks of every type.
+fn sequence() -> TokenStream {
+    quote! {
+        /// An order for deferred expectations, which are added to it through `in_sequence`. A
+        /// method may come more than once in the order, and each of its calls counts for the
+        /// first of its expectations that still expects calls.
+        #[derive(Clone, Default)]
+        pub struct Sequence(
+            ::std::sync::Arc<::std::sync::Mutex<(Vec<String>, Vec<usize>)>>,
+        );
+
+        impl Sequence {
+            pub fn new() -> Self {
+                Self::default()
+            }
+        }
+
+        impl AsRef<::std::sync::Arc<::std::sync::Mutex<(Vec<String>, Vec<usize>)>>>
+            for Sequence
+        {
+            fn as_ref(&self) -> &::std::sync::Arc<::std::sync::Mutex<(Vec<String>, Vec<usize>)>> {
+                &self.0
+            }
+        }
+    }
+}
+
+/// Implements `Arguments` for the tuples of `count` matchers, which match the arguments of the
+/// methods with `count` arguments.
+fn arguments_impl(count: usize) -> TokenStream {
+    let types: Vec<_> = (0..count).map(|i| format_ident!("T{i}")).collect();
+    let matchers: Vec<_> = (0..count).map(|i| format_ident!("M{i}")).collect();
+    let positions = (0..count).map(|i| Index {
+        index: u32::try_from(i).expect("there are only a few arguments"),
+        span: Span::call_site(),
+    });
+    let indices = 0..count;
+
+    quote! {
+        impl<#(#types: ?Sized,)* #(#matchers,)*> Arguments<fn(#(&#types),*)> for (#(#matchers,)*)
+        where
+            #(#matchers: Matcher<#types>,)*
+        {
+            fn mismatch(&self, values: &[Option<&dyn Any>]) -> Option<(usize, String)> {
+                #(
+                    if !Matcher::<#types>::matches(&self.#positions, values[#indices]) {
+                        let mut expected = Matcher::<#types>::describe(&self.#positions);
+                        if values[#indices].is_none() {
+                            expected.push_str(
+                                ", but the argument could not be recorded as it cannot be cloned, sent or may borrow",
+                            );
+                        }
+                        return Option::Some((#indices, expected));
+                    }
+                )*
+                Option::None
+            }
+        }
+    }
+}
@@ -0,0 +1,200 @@
+use quote::{ToTokens, format_ident, quote};
+use syn::{Ident, Item, LitStr, Visibility, meta::ParseNestedMeta, parse_quote};
+
+/// The arguments given to the macros, such as `#[mock(always)]`.
+pub struct Options {
+    /// The `cfg` predicate under which the mocks are generated, or `None` to always generate them.
+    gate: Option<proc_macro2::TokenStream>,
+    naming: Naming,
+    /// The `spy` option, which keeps the original bodies of the mocked methods.
+    spy: Option<syn::Path>,
+    /// The `convert` option, which generates the conversions between a type and its mock.
+    convert: Option<syn::Path>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            gate: Some(quote!(test)),
+            naming: Naming::default(),
+            spy: None,
+            convert: None,
+        }
+    }
+}
+
+/// How the mocks are named after the types they replace. Without any option, `Foo` is mocked by
+/// `FooMock`. Giving a prefix or a suffix replaces the `Mock` suffix, so `prefix = "Mock"` names
+/// the mock `MockFoo`.
+#[derive(Clone, Default)]
+pub struct Naming {
+    /// The name of the mock, which replaces the derived one.
+    name: Option<Ident>,
+    prefix: Option<String>,
+    suffix: Option<String>,
+    /// Whether the mocks are looked up by their name in the current scope, as for the mocks of
+    /// `mock_foreign!`, instead of next to the original types.
+    in_scope: bool,
+    /// The visibility of the mocks generated for a field, such as those of function pointers,
+    /// which is `pub` unless the field is less visible.
+    visibility: Option<Visibility>,
+}
+
+impl Naming {
+    /// The name of the mock of `original`.
+    pub fn mock_name(&self, original: &Ident) -> Ident {
+        if let Some(name) = &self.name {
+            return name.clone();
+        }
+        match (&self.prefix, &self.suffix) {
+            (None, None) => format_ident!("{original}Mock"),
+            (prefix, suffix) => format_ident!(
+                "{}{original}{}",
+                prefix.as_deref().unwrap_or_default(),
+                suffix.as_deref().unwrap_or_default()
+            ),
+        }
+    }
+
+    /// The same naming, with the mocks looked up in the current scope. Used for the fields marked
+    /// with `#[mocked(foreign)]`.
+    pub fn in_scope(&self) -> Self {
+        Self {
+            in_scope: true,
+            ..self.clone()
+        }
+    }
+
+    pub const fn is_in_scope(&self) -> bool {
+        self.in_scope
+    }
+
+    /// The same naming, with the mocks generated for a field as visible as the field.
+    pub fn visible_as(&self, visibility: &Visibility) -> Self {
+        Self {
+            visibility: Some(visibility.clone()),
+            ..self.clone()
+        }
+    }
+
+    pub fn visibility(&self) -> Visibility {
+        self.visibility.clone().unwrap_or_else(|| parse_quote!(pub))
+    }
+
+    /// The name given with `name = "..."`, if any.
+    pub const fn name(&self) -> Option<&Ident> {
+        self.name.as_ref()
+    }
+}
+
+impl Options {
+    /// Parses a single argument of the macro. Used with [`syn::meta::parser`].
+    pub fn parse(&mut self, meta: &ParseNestedMeta<'_>) -> syn::Result<()> {
+        if meta.path.is_ident("always") {
+            self.gate = None;
+            Ok(())
+        } else if meta.path.is_ident("cfg") {
+            let predicate: LitStr = meta.value()?.parse()?;
+            self.gate = Some(predicate.parse()?);
+            Ok(())
+        } else if meta.path.is_ident("spy") {
+            self.spy = Some(meta.path.clone());
+            Ok(())
+        } else if meta.path.is_ident("convert") {
+            self.convert = Some(meta.path.clone());
+            Ok(())
+        } else if meta.path.is_ident("name") {
+            let name: LitStr = meta.value()?.parse()?;
+            self.naming.name = Some(name.parse()?);
+            Ok(())
+        } else if meta.path.is_ident("prefix") {
+            self.naming.prefix = Some(affix(meta)?);
+            Ok(())
+        } else if meta.path.is_ident("suffix") {
+            self.naming.suffix = Some(affix(meta)?);
+            Ok(())
+        } else {
+            Err(meta.error(
+                "expected `cfg = \"predicate\"`, `always`, `spy`, `convert`, `name = \"Name\"`, \
+                 `prefix = \"Prefix\"` or `suffix = \"Suffix\"`",
+            ))
+        }
+    }
+
+    pub const fn naming(&self) -> &Naming {
+        &self.naming
+    }
+
+    pub const fn spy(&self) -> Option<&syn::Path> {
+        self.spy.as_ref()
+    }
+
+    pub const fn convert(&self) -> Option<&syn::Path> {
+        self.convert.as_ref()
+    }
+
+    /// Puts each of the generated items under the `cfg` of the mocks. Errors are left out so that
+    /// they are reported in every build.
+    pub fn gate(&self, tokens: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+        let Some(gate) = &self.gate else {
+            return tokens;
+        };
+        match syn::parse2::<syn::File>(tokens) {
+            Ok(file) => file
+                .items
+                .iter()
+                .map(|item| match item {
+                    Item::Macro(m)
+                        if m.mac
+                            .path
+                            .segments
+                            .last()
+                            .is_some_and(|segment| segment.ident == "compile_error") =>
+                    {
+                        quote!(#item)
+                    }
+                    _ => quote!(#[cfg(#gate)] #item),
+                })
+                .collect(),
+            Err(error) => error.to_compile_error(),
+        }
+    }
+
+    /// Uses `mocked` in the builds where the mocks exist and `original` in the others.
+    pub fn switch(
+        &self,
+        original: &impl ToTokens,
+        mocked: proc_macro2::TokenStream,
+    ) -> proc_macro2::TokenStream {
+        let gated = self.gate(mocked);
+        match &self.gate {
+            Some(gate) => quote! {
+                #[cfg(not(#gate))]
+                #original
+
+                #gated
+            },
+            None => gated,
+        }
+    }
+}
+
+/// Parses the value of `prefix` or `suffix`, which has to be usable as part of an identifier. An
+/// empty one is rejected, as it could leave the mock with the name of the original.
+fn affix(meta: &ParseNestedMeta<'_>) -> syn::Result<String> {
+    let affix: LitStr = meta.value()?.parse()?;
+    let value = affix.value();
+    if value.is_empty() {
+        Err(syn::Error::new(
+            affix.span(),
+            "expected a non-empty prefix or suffix to put around the names of the mocks",
+        ))
+    } else if value.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        Ok(value)
+    } else {
+        Err(syn::Error::new(
+            affix.span(),
+            "expected letters, digits and underscores to put around the names of the mocks",
+        ))
+    }
+}
@@ -1,144 +1,553 @@
 use std::ops::Deref;
 
 use proc_macro::TokenStream;
-use quote::quote;
+use proc_macro2::{Span, TokenTree};
+use quote::{format_ident, quote};
 use syn::{
-    FnArg, ImplItem, ImplItemFn, ImplItemType, ItemImpl, ReturnType, Type, TypePath, parse,
-    parse_str,
+    Block, Expr, ExprPath, ExprStruct, FnArg, GenericParam, Generics, Ident, ImplItem, ImplItemFn,
+    ImplItemType, ItemImpl, Pat, PatRest, PatStruct, PatTupleStruct, Path, PathSegment, ReturnType,
+    Signature, Token, Type, TypePath, parse_quote,
+    visit_mut::{self, VisitMut},
 };
 
-use crate::extract::{ExtractName, Extracted, prepare_mock_name};
+use crate::builder::snake_case;
+use crate::extract::{ExtractName, Extracted, prepare_mock_name, take_mocked_with};
+use crate::options::Naming;
+use crate::record::{
+    argument_signature, argument_types, bind_arguments, expectation_name, instance_id, record_call,
+    returning_call,
+};
 
 pub struct MockContext {
-    original_type: Box<Type>,
+    /// Whether the mocked methods keep their original body, as set by `#[mock_impl(spy)]`.
+    spy: bool,
+    original_extract: Extracted,
     mocked_extract: Extracted,
 }
 
 impl MockContext {
-    pub fn new(original_type: Box<Type>) -> Self {
-        let mock_name = prepare_mock_name(&original_type.extract_name());
-        Self {
-            original_type,
+    pub fn new(original_type: &Type, naming: &Naming) -> syn::Result<Self> {
+        let original_extract = original_type.extract_name()?;
+        let mock_name = prepare_mock_name(&original_extract, naming);
+        Ok(Self {
+            spy: false,
+            original_extract,
             mocked_extract: mock_name,
-        }
+        })
+    }
+
+    /// Makes the mocked methods run their original body when no value is configured for them.
+    pub fn spying(mut self, spy: bool) -> Self {
+        self.spy = spy;
+        self
     }
 
-    #[allow(clippy::redundant_clone)]
     pub fn mock_impl(&self, tokens: ItemImpl) -> TokenStream {
-        let original_type = tokens.self_ty.clone();
-        let original_name = original_type.extract_name();
-        let Extracted { name, .. } = prepare_mock_name(&original_name);
-
-        if let Some((tr, _)) = tokens.trait_ {
-            let functions = tokens.items.into_iter().map(|item| match item {
-                ImplItem::Fn(f) => self.replace_self_from_function_with_mocks(f),
-                ImplItem::Type(mut ty) => {
-                    self.replace_mocks_in_associated_types(&mut ty);
-                    ImplItem::Type(ty)
-                }
-                _ => todo!(),
-            });
+        let name = &self.mocked_extract.name;
+        let trait_ = tokens.trait_.as_ref().map(|(tr, _)| {
+            let mut tr = tr.clone();
+            self.replace_original().visit_path_mut(&mut tr);
+            tr
+        });
+        let trait_ = trait_.as_ref();
+        let qualifier = tokens
+            .trait_
+            .as_ref()
+            .and_then(|(tr, _)| tr.segments.last())
+            .cloned();
+        let qualifier = qualifier.as_ref();
+        let methods: Vec<String> = tokens
+            .items
+            .iter()
+            .filter_map(|item| match item {
+                ImplItem::Fn(f) => Some(method_key(qualifier, &f.sig.ident)),
+                _ => None,
+            })
+            .collect();
+        let (items, helpers): (Vec<_>, Vec<_>) = tokens
+            .items
+            .into_iter()
+            .map(|item| self.mock_impl_item(item, &tokens.generics, trait_, qualifier, &methods))
+            .unzip();
+
+        let mut impl_generics = tokens.generics.clone();
+        self.replace_original()
+            .visit_generics_mut(&mut impl_generics);
+        let (impl_generics, _, where_clause) = impl_generics.split_for_impl();
+        let mut generics = self.original_extract.generics.clone();
+        if let Some(generics) = &mut generics {
+            self.replace_original()
+                .visit_angle_bracketed_generic_arguments_mut(generics);
+        }
 
-            let Extracted {
-                name: trait_,
-                generics: trait_generics,
-            } = tr.extract_name();
-            let impl_generics = tokens.generics.clone();
+        let attrs = &tokens.attrs;
+        let cfgs = attrs.iter().filter(|attr| attr.path().is_ident("cfg"));
+        let unsafety = &tokens.unsafety;
 
+        if let Some(tr) = trait_ {
             TokenStream::from(quote! {
-                impl #impl_generics #trait_ #trait_generics for #name {
-                    #(#functions)*
+                #(#attrs)*
+                #unsafety impl #impl_generics #tr for #name #generics #where_clause {
+                    #(#items)*
+                }
+
+                #(#cfgs)*
+                impl #impl_generics #name #generics #where_clause {
+                    #(#helpers)*
                 }
             })
         } else {
-            let functions = tokens.items.into_iter().map(|item| match item {
-                ImplItem::Fn(f) => self.replace_self_from_function_with_mocks(f),
-                _ => unreachable!(),
-            });
-
-            let impl_generics = tokens.generics.clone();
-            let generics = original_name.generics.clone();
-
             TokenStream::from(quote! {
-                impl #impl_generics #name #generics {
-                    #(#functions)*
+                #(#attrs)*
+                impl #impl_generics #name #generics #where_clause {
+                    #(#items)*
+                    #(#helpers)*
                 }
             })
         }
     }
 
-    #[allow(clippy::explicit_deref_methods)]
-    fn replace_self_from_function_with_mocks(&self, mut f: ImplItemFn) -> ImplItem {
-        let visibility = f.vis;
+    /// Carries an item of the implementation over to the mock. Functions are mocked, constants
+    /// take their value from `#[mocked_with(value)]` when it is given, and the other items, such
+    /// as macros, are kept as they are written.
+    fn mock_impl_item(
+        &self,
+        item: ImplItem,
+        generics_in_scope: &Generics,
+        trait_: Option<&Path>,
+        qualifier: Option<&PathSegment>,
+        methods: &[String],
+    ) -> (ImplItem, proc_macro2::TokenStream) {
+        match item {
+            ImplItem::Fn(f) => self.replace_self_from_function_with_mocks(
+                f,
+                generics_in_scope,
+                trait_,
+                qualifier,
+                methods,
+            ),
+            ImplItem::Const(mut c) => {
+                if let Some(value) = take_mocked_with(&mut c.attrs) {
+                    c.expr = Expr::Verbatim(value);
+                }
+                (ImplItem::Const(c), proc_macro2::TokenStream::new())
+            }
+            ImplItem::Type(mut ty) => {
+                self.replace_mocks_in_associated_types(&mut ty);
+                (ImplItem::Type(ty), proc_macro2::TokenStream::new())
+            }
+            other => (other, proc_macro2::TokenStream::new()),
+        }
+    }
 
-        f.sig
-            .inputs
-            .iter_mut()
-            .filter_map(|arg| {
-                if let FnArg::Typed(typ) = arg
-                    && typ.ty == self.original_type
-                    && let Type::Path(p) = &mut *typ.ty
+    /// Generates the functions through which tests inspect and configure a mocked method. These
+    /// live in an inherent impl, so associated types of the trait being implemented are qualified
+    /// with it, and so are the names of the functions when a `qualifier` is given.
+    fn generate_helpers(
+        &self,
+        sig: &Signature,
+        output: Option<&proc_macro2::TokenStream>,
+        configurable: bool,
+        trait_: Option<&Path>,
+        qualifier: Option<&PathSegment>,
+        methods: &[String],
+    ) -> proc_macro2::TokenStream {
+        let mut sig = sig.clone();
+        let mut output: Option<Type> = output.map(|output| parse_quote!(#output));
+        if let Some(trait_) = trait_ {
+            let mut qualify = QualifySelf(trait_);
+            qualify.visit_signature_mut(&mut sig);
+            if let Some(ty) = &mut output {
+                qualify.visit_type_mut(ty);
+            }
+        }
+
+        let expectation = expectation_name(&self.mocked_extract.name);
+        let helper = helper_name(qualifier, &sig.ident);
+        let expect = format_ident!("expect_{helper}");
+        let method = method_key(qualifier, &sig.ident);
+        let id = instance_id(&sig);
+
+        let signature = if configurable {
+            argument_signature(&sig)
+        } else {
+            quote!(())
+        };
+        let expect = quote! {
+            pub fn #expect(&self) -> #expectation<#signature> {
+                #expectation::new(
+                    #method,
+                    &[#(#methods),*],
+                    #id,
+                    Self::__mocktoffel_calls(#id, |calls| {
+                        calls
+                            .iter()
+                            .filter(|(called, ..)| *called == #method)
+                            .map(|(_, arguments, values)| (arguments.clone(), values.clone()))
+                            .collect()
+                    }),
+                )
+            }
+        };
+
+        if !configurable {
+            return expect;
+        }
+
+        let returning = format_ident!("returning_{helper}");
+        let return_const = format_ident!("return_const_{helper}");
+        let inputs = argument_types(&sig);
+        let return_const_fn = output.as_ref().map(|output| {
+            quote! {
+                pub fn #return_const<V>(&self, value: V)
+                where
+                    V: ::std::clone::Clone
+                        + ::std::convert::Into<#output>
+                        + ::std::marker::Send
+                        + 'static,
                 {
-                    Some(p)
-                } else {
-                    None
+                    self.#returning(move |_| value.clone().into());
                 }
-            })
-            .for_each(|p| {
-                let segments = p.path.segments.last_mut().unwrap();
-                let fn_generics = segments.arguments.clone();
-                *p = parse_str(format!("{}", self.mocked_extract.name).as_str()).unwrap();
+            }
+        });
+        let output = output.map(|output| quote!(-> #output));
 
-                let segment = p.path.segments.last_mut().unwrap();
-                segment.arguments = fn_generics;
-            });
+        quote! {
+            #expect
+
+            pub fn #returning(
+                &self,
+                returning: impl FnMut((#(#inputs,)*)) #output + ::std::marker::Send + 'static,
+            ) {
+                let returning: ::std::boxed::Box<
+                    dyn FnMut((#(#inputs,)*)) #output + ::std::marker::Send,
+                > = ::std::boxed::Box::new(returning);
+                Self::__mocktoffel_returning(#id, |returned| {
+                    returned.insert(
+                        #method,
+                        ::std::sync::Arc::new(::std::sync::Mutex::new(returning)),
+                    );
+                });
+            }
+
+            #return_const_fn
+        }
+    }
+
+    #[allow(clippy::explicit_deref_methods)]
+    pub fn replace_self_from_function_with_mocks(
+        &self,
+        mut f: ImplItemFn,
+        generics_in_scope: &Generics,
+        trait_: Option<&Path>,
+        qualifier: Option<&PathSegment>,
+        methods: &[String],
+    ) -> (ImplItem, proc_macro2::TokenStream) {
+        self.replace_original().visit_signature_mut(&mut f.sig);
+        let method = method_key(qualifier, &f.sig.ident);
 
-        let inputs = f.sig.inputs.iter();
+        let mocked_with = take_mocked_with(&mut f.attrs);
+        // Calls cannot be recorded during constant evaluation.
+        f.sig.constness = None;
 
-        let function_name = f.sig.ident;
-        let name = self.mocked_extract.name.clone();
-        let original_name = self.original_type.extract_name();
-        let generics = original_name.generics;
-        syn::parse(match &f.sig.output {
-            ReturnType::Type(_, p)
-                if let Type::Path(TypePath { path: pat, .. }) = p.deref()
-                    && pat.extract_name().name == original_name.name =>
+        let patterns: Vec<Pat> = f
+            .sig
+            .inputs
+            .iter()
+            .filter_map(|arg| match arg {
+                FnArg::Typed(typ) => Some((*typ.pat).clone()),
+                FnArg::Receiver(_) => None,
+            })
+            .collect();
+        let arguments = bind_arguments(&mut f.sig);
+        let record = record_call(&f.sig, &method, &arguments, generics_in_scope);
+        let (output, default) = match &f.sig.output {
+            ReturnType::Type(_, ty)
+                if let Type::Path(TypePath { path, .. }) = ty.deref()
+                    && path
+                        .extract_name()
+                        .is_ok_and(|extracted| extracted.name == self.mocked_extract.name) =>
             {
-                if pat.extract_name().name == original_name.name {
-                    TokenStream::from(quote! {
-                        #visibility fn #function_name (#(#inputs),*) -> #name #generics {
-                            <#name>::mock_new()
-                        }
-                    })
-                } else {
-                    TokenStream::from(quote!(f))
-                }
+                (Some(quote!(#ty)), quote!(<#ty>::mock_new()))
             }
+            ReturnType::Type(_, ty) => (Some(quote!(#ty)), quote!(Default::default())),
+            ReturnType::Default => (None, quote!()),
+        };
+        let default = match mocked_with {
+            Some(value) => value,
+            None if self.spy => self.original_body(&f.block, &patterns, &arguments),
+            None => default,
+        };
+        let configurable = supports_returning(&f.sig, generics_in_scope);
+        let returning =
+            configurable.then(|| returning_call(&f.sig, &method, &arguments, output.as_ref()));
+        let helpers = self.generate_helpers(
+            &f.sig,
+            output.as_ref(),
+            configurable,
+            trait_,
+            qualifier,
+            methods,
+        );
 
-            ReturnType::Type(_, ty) => TokenStream::from(quote! {
-                #visibility fn #function_name(#(#inputs), *) -> #ty {
-                    Default::default()
-                }
-            }),
+        f.block = parse_quote!({
+            #record
+            #returning
+            #default
+        });
 
-            ReturnType::Default => TokenStream::from(quote! {
-                #visibility fn #function_name(#(#inputs), *) {
-                }
-            }),
-        })
-        .unwrap()
+        (ImplItem::Fn(f), helpers)
+    }
+
+    /// The original body of a method, which spies run when no value is configured. The arguments
+    /// that were given a name to record them are destructured again, and the original type is
+    /// replaced with the mock. The statements are spliced into the body of the mock rather than
+    /// kept in a block, which would be reported as unnecessary braces around its value.
+    fn original_body(
+        &self,
+        block: &Block,
+        patterns: &[Pat],
+        arguments: &[Ident],
+    ) -> proc_macro2::TokenStream {
+        let mut block = block.clone();
+        self.replace_original().visit_block_mut(&mut block);
+        let rebound = patterns
+            .iter()
+            .zip(arguments)
+            .filter(|(pattern, argument)| {
+                !matches!(pattern, Pat::Ident(pattern) if pattern.ident == **argument)
+            })
+            .map(|(pattern, argument)| quote!(let #pattern = #argument;));
+
+        let statements = &block.stmts;
+
+        quote! {
+            #(#rebound)*
+            #(#statements)*
+        }
     }
 
     fn replace_mocks_in_associated_types(&self, associated_type: &mut ImplItemType) {
-        let Extracted { name, generics } = associated_type.ty.extract_name();
-        if name == self.original_type.extract_name().name {
-            let original_type = self.original_type.clone();
-            let Extracted {
-                name: mocked_name,
-                generics: mocked_generics,
-            } = prepare_mock_name(&original_type.extract_name());
-            associated_type.ty =
-                parse(TokenStream::from(quote! { #mocked_name #mocked_generics })).unwrap();
+        self.replace_original()
+            .visit_impl_item_type_mut(associated_type);
+    }
+
+    /// A visitor that rewrites the original type into the mock wherever it occurs.
+    pub fn replace_original(&self) -> ReplaceOriginal<'_> {
+        ReplaceOriginal {
+            original: &self.original_extract.name,
+            mocked: &self.mocked_extract.name,
+        }
+    }
+}
+
+/// The name under which the calls of a method are recorded, which is qualified with the trait it
+/// implements and its generic arguments, as in `Display::fmt` or `From<u8>::from`, so that it is
+/// kept apart from an inherent method of the same name and from the other implementations of the
+/// trait.
+pub fn method_key(qualifier: Option<&PathSegment>, method: &Ident) -> String {
+    qualifier.map_or_else(
+        || method.to_string(),
+        |qualifier| format!("{}::{method}", compact(&quote!(#qualifier).to_string())),
+    )
+}
+
+/// The name of a method in the names of its helpers, such as `display_fmt` in `expect_display_fmt`
+/// for the `fmt` method of `Display`, or `from_u8_from` for the `from` method of `From<u8>`.
+fn helper_name(qualifier: Option<&PathSegment>, method: &Ident) -> Ident {
+    qualifier.map_or_else(
+        || method.clone(),
+        |qualifier| {
+            let arguments = &qualifier.arguments;
+            let mut words = vec![snake_case(&qualifier.ident)];
+            argument_words(quote!(#arguments), &mut words);
+            format_ident!("{}_{method}", words.join("_"))
+        },
+    )
+}
+
+/// Collects the names and literals in the generic arguments of a trait, in snake case, leaving
+/// out the lifetimes.
+fn argument_words(tokens: proc_macro2::TokenStream, words: &mut Vec<String>) {
+    let mut lifetime = false;
+    for token in tokens {
+        let apostrophe = matches!(&token, TokenTree::Punct(punct) if punct.as_char() == '\'');
+        match token {
+            TokenTree::Group(group) => argument_words(group.stream(), words),
+            TokenTree::Ident(ident) if !lifetime => words.push(snake_case(&ident)),
+            TokenTree::Literal(literal) => words.push(
+                literal
+                    .to_string()
+                    .chars()
+                    .filter(char::is_ascii_alphanumeric)
+                    .collect(),
+            ),
+            _ => {}
+        }
+        lifetime = apostrophe;
+    }
+}
+
+/// Removes the spaces that the tokens are printed with, except between two words.
+fn compact(printed: &str) -> String {
+    let chars: Vec<char> = printed.chars().collect();
+    let is_word = |c: Option<&char>| c.is_some_and(|c| c.is_alphanumeric() || *c == '_');
+    chars
+        .iter()
+        .enumerate()
+        .filter(|&(i, c)| {
+            *c != ' '
+                || (is_word(i.checked_sub(1).and_then(|i| chars.get(i)))
+                    && is_word(chars.get(i + 1)))
+        })
+        .map(|(_, c)| c)
+        .collect()
+}
+
+/// Closures installed through `returning_<method>` are stored as `dyn Any`, which requires the
+/// types of the arguments and the return value to be nameable and `'static`.
+fn supports_returning(sig: &Signature, generics_in_scope: &Generics) -> bool {
+    let generics: Vec<&Ident> = generics_in_scope
+        .params
+        .iter()
+        .chain(sig.generics.params.iter())
+        .filter_map(|param| match param {
+            GenericParam::Type(ty) => Some(&ty.ident),
+            GenericParam::Const(c) => Some(&c.ident),
+            GenericParam::Lifetime(_) => None,
+        })
+        .collect();
+    let inputs = argument_types(sig);
+    let output = &sig.output;
+
+    !is_unnameable(quote!(#(#inputs)*), &generics, false)
+        && !is_unnameable(quote!(#output), &generics, true)
+}
+
+fn is_unnameable(tokens: proc_macro2::TokenStream, generics: &[&Ident], borrows: bool) -> bool {
+    tokens.into_iter().any(|token| match token {
+        TokenTree::Group(group) => is_unnameable(group.stream(), generics, borrows),
+        TokenTree::Ident(ident) => ident == "impl" || generics.contains(&&ident),
+        TokenTree::Punct(punct) => punct.as_char() == '\'' || (borrows && punct.as_char() == '&'),
+        TokenTree::Literal(_) => false,
+    })
+}
+
+/// Rewrites `Self::Assoc` into `<Self as Trait>::Assoc`.
+struct QualifySelf<'a>(&'a Path);
+
+impl VisitMut for QualifySelf<'_> {
+    fn visit_type_path_mut(&mut self, ty: &mut TypePath) {
+        if ty.qself.is_none()
+            && ty.path.segments.len() > 1
+            && ty.path.segments.first().is_some_and(|s| s.ident == "Self")
+        {
+            let trait_ = self.0;
+            let rest = ty.path.segments.iter().skip(1);
+            *ty = parse_quote!(<Self as #trait_>#(::#rest)*);
+        }
+        visit_mut::visit_type_path_mut(self, ty);
+    }
+}
+
+/// Rewrites every occurrence of the original type into the mock, so that `&Foo`, `Option<Foo>`
+/// and `Result<Foo, Error>` become `&FooMock`, `Option<FooMock>` and `Result<FooMock, Error>`.
+/// Paths to the original, such as `http::Client`, are replaced with the name of the mock, which is
+/// in scope where the implementation for the mock is generated.
+pub struct ReplaceOriginal<'a> {
+    original: &'a Ident,
+    mocked: &'a Ident,
+}
+
+impl VisitMut for ReplaceOriginal<'_> {
+    fn visit_type_path_mut(&mut self, ty: &mut TypePath) {
+        if ty.qself.is_none()
+            && let Some(mut segment) = ty.path.segments.pop()
+        {
+            if segment.ident == *self.original {
+                segment.ident = self.mocked.clone();
+                ty.path.segments.clear();
+                ty.path.leading_colon = None;
+            }
+            ty.path.segments.push(segment);
+        }
+        visit_mut::visit_type_path_mut(self, ty);
+    }
+
+    fn visit_expr_path_mut(&mut self, expr: &mut ExprPath) {
+        self.rename(&mut expr.path);
+        visit_mut::visit_expr_path_mut(self, expr);
+    }
+
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        visit_mut::visit_expr_mut(self, expr);
+        if let Expr::Struct(literal) = expr
+            && literal.fields.len() == 1
+            && self.completes_mock(literal)
+        {
+            let path = &literal.path;
+            let field = &literal.fields[0];
+            let (member, value) = (&field.member, &field.expr);
+            *expr = parse_quote!(#path::mock_with(|mock| mock.#member = #value));
+        }
+    }
+
+    fn visit_expr_struct_mut(&mut self, expr: &mut ExprStruct) {
+        self.rename(&mut expr.path);
+        if expr.fields.len() != 1 && self.completes_mock(expr) {
+            expr.fields
+                .push(parse_quote!(__mocktoffel: ::std::default::Default::default()));
+        }
+        visit_mut::visit_expr_struct_mut(self, expr);
+    }
+
+    fn visit_pat_struct_mut(&mut self, pat: &mut PatStruct) {
+        self.rename(&mut pat.path);
+        if pat.rest.is_none() && self.builds_mock(&pat.path) {
+            pat.rest = Some(PatRest {
+                attrs: Vec::new(),
+                dot2_token: Token![..](Span::call_site()),
+            });
+        }
+        visit_mut::visit_pat_struct_mut(self, pat);
+    }
+
+    fn visit_pat_tuple_struct_mut(&mut self, pat: &mut PatTupleStruct) {
+        self.rename(&mut pat.path);
+        visit_mut::visit_pat_tuple_struct_mut(self, pat);
+    }
+}
+
+impl ReplaceOriginal<'_> {
+    /// Whether the path of a struct expression or pattern is the mock itself, as in `Self`,
+    /// `FooMock` or `module::FooMock`, which holds the hidden field identifying its instance when
+    /// it is a struct. It is filled in with a new instance in expressions, and left out of
+    /// patterns.
+    fn builds_mock(&self, path: &Path) -> bool {
+        path.is_ident("Self")
+            || path
+                .segments
+                .last()
+                .is_some_and(|segment| segment.ident == *self.mocked)
+    }
+
+    /// Whether the struct expression builds the mock from all of its fields. As the mocks of
+    /// unions are built from a single field and hold no hidden field, the expressions with a
+    /// single field are built from `mock_with` instead, which fills in the rest of either.
+    fn completes_mock(&self, expr: &ExprStruct) -> bool {
+        expr.qself.is_none()
+            && expr.rest.is_none()
+            && expr.dot2_token.is_none()
+            && self.builds_mock(&expr.path)
+    }
+
+    /// Renames the original type in the paths of expressions and patterns, such as `Foo::new()`
+    /// and `Foo { .. }`.
+    fn rename(&self, path: &mut Path) {
+        for segment in &mut path.segments {
+            if segment.ident == *self.original {
+                segment.ident = self.mocked.clone();
+            }
         }
     }
 }
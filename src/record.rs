@@ -0,0 +1,1295 @@
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use syn::{
+    Attribute, Field, Fields, FieldsNamed, FnArg, GenericArgument, Generics, Ident, Member, Pat,
+    PatIdent, Path, PathArguments, PathSegment, Signature, Token, Type, Visibility, parse_quote,
+    punctuated::Punctuated,
+};
+
+/// How the mocks of a type tell their instances apart, so that each of them has its own state.
+pub enum Tracking {
+    /// The mock holds its identifier in the hidden field at the given member, and its clones get
+    /// a new one.
+    Owned(Member),
+    /// The mock is `Copy`, so its copies share the identifier held in the hidden field at the
+    /// given member. It cannot be dropped, so its expectations cannot be deferred.
+    Copied(Member),
+    /// The mock has no hidden field, as for enums, unions, unit and tuple structs, so all the
+    /// mocks of the type share their state and their expectations cannot be deferred.
+    Shared,
+}
+
+/// Name of the type returned by the `expect_<method>` functions of a mock.
+pub fn expectation_name(mock_name: &Ident) -> Ident {
+    Ident::new(&format!("{mock_name}Expectation"), Span::call_site())
+}
+
+/// Name of the type identifying each instance of a mock, held in its hidden `__mocktoffel` field.
+pub fn instance_name(mock_name: &Ident) -> Ident {
+    Ident::new(&format!("{mock_name}Instance"), Span::call_site())
+}
+
+/// Adds the hidden field identifying the instances of a mock to its fields when they are named,
+/// and returns how the mock tracks its instances. Unit and tuple structs keep their shape, so that
+/// they can still be built as such, and share their state instead.
+pub fn add_instance_field(fields: &mut Fields, attrs: &[Attribute], mock_name: &Ident) -> Tracking {
+    let Fields::Named(named) = fields else {
+        return Tracking::Shared;
+    };
+    let instance = instance_name(mock_name);
+    let hidden: FieldsNamed = parse_quote!({
+        #[doc(hidden)]
+        pub __mocktoffel: #instance
+    });
+    named.named.extend(hidden.named);
+
+    let member = Member::Named(Ident::new("__mocktoffel", Span::call_site()));
+    if is_copy(attrs) {
+        Tracking::Copied(member)
+    } else {
+        Tracking::Owned(member)
+    }
+}
+
+/// Whether the field is the hidden field identifying the instances of the mock.
+pub fn is_instance_field(field: &Field) -> bool {
+    field
+        .ident
+        .as_ref()
+        .is_some_and(|ident| ident == "__mocktoffel")
+}
+
+/// Name of the type holding the deferred expectations of a mock until they are verified.
+fn deferred_name(mock_name: &Ident) -> Ident {
+    Ident::new(
+        &format!("{mock_name}DeferredExpectation"),
+        Span::call_site(),
+    )
+}
+
+/// Generates the call log of a mock along with the expectation type used to inspect it, the store
+/// of the closures configured to produce the return values of its methods, and the deferred
+/// expectations verified by `checkpoint`.
+///
+/// They are kept in stores shared by all threads, so that a mock records its calls wherever it is
+/// moved or shared to, as with `Arc` or a multi-threaded runtime. The calls and the deferred
+/// expectations belong to the instance of the mock they were made on, as told apart by `tracking`,
+/// and are verified and forgotten when it is dropped. The functions without a receiver, and the
+/// mocks that do not tell their instances apart, have no instance to carry across threads, so they
+/// keep theirs per thread and each test sees only its own.
+pub fn generate_mock_state(
+    vis: &Visibility,
+    mock_name: &Ident,
+    generics: &Generics,
+    tracking: &Tracking,
+) -> TokenStream {
+    let expectation_type = generate_expectation(vis, mock_name, tracking);
+    let deferred_type = generate_deferred(vis, mock_name);
+    let instance_type = generate_instance(vis, mock_name, tracking);
+    let verification = generate_verification(mock_name);
+    let instance = instance_name(mock_name);
+    let value = recorded_value();
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let id = match tracking {
+        Tracking::Owned(member) | Tracking::Copied(member) => quote!(self.#member.id()),
+        Tracking::Shared => quote!(0),
+    };
+    let checkpoint = matches!(tracking, Tracking::Owned(_)).then(|| {
+        quote! {
+            /// Verifies the deferred expectations configured on this mock, and on the functions
+            /// of its type without a receiver, so far and forgets them.
+            #[track_caller]
+            pub fn checkpoint(&self) {
+                #instance::verify(self.__mocktoffel_id());
+                #instance::verify(0);
+            }
+        }
+    });
+
+    quote! {
+        #expectation_type
+
+        #deferred_type
+
+        #instance_type
+
+        #verification
+
+        impl #impl_generics #mock_name #ty_generics #where_clause {
+            #checkpoint
+
+            #[doc(hidden)]
+            pub fn __mocktoffel_id(&self) -> u64 {
+                #id
+            }
+
+            #[doc(hidden)]
+            pub fn __mocktoffel_calls<R>(
+                id: u64,
+                f: impl FnOnce(&mut ::std::vec::Vec<(
+                    &'static str,
+                    ::std::vec::Vec<::std::string::String>,
+                    ::std::vec::Vec<#value>,
+                )>) -> R,
+            ) -> R {
+                #instance::calls(id, f)
+            }
+
+            #[doc(hidden)]
+            pub fn __mocktoffel_sequenced(
+                id: u64,
+                method: &'static str,
+                values: &[#value],
+            ) {
+                #instance::sequenced(id, method, values);
+            }
+
+            #[doc(hidden)]
+            pub fn __mocktoffel_returning<R>(
+                id: u64,
+                f: impl FnOnce(
+                    &mut ::std::collections::HashMap<
+                        &'static str,
+                        ::std::sync::Arc<
+                            dyn ::std::any::Any + ::std::marker::Send + ::std::marker::Sync,
+                        >,
+                    >,
+                ) -> R,
+            ) -> R {
+                #instance::returning(id, f)
+            }
+        }
+    }
+}
+
+/// Generates the type identifying each instance of a mock, which holds the state of the mock kept
+/// in shared stores so that it can be reached from its `Drop` whatever the generics of the mock.
+/// The identifier 0 is left to the functions without a receiver, and to the mocks that do not
+/// tell their instances apart, and stands for a key of its own on each thread. The identifier is
+/// ignored by the comparisons and the hash of the mock, so that two mocks with the same values are
+/// equal.
+fn generate_instance(vis: &Visibility, mock_name: &Ident, tracking: &Tracking) -> TokenStream {
+    let instance = instance_name(mock_name);
+    let stores = generate_stores(mock_name);
+    let comparisons = generate_comparisons(&instance);
+    let clone = if matches!(tracking, Tracking::Owned(_)) {
+        quote! {
+            impl ::std::clone::Clone for #instance {
+                fn clone(&self) -> Self {
+                    Self::new()
+                }
+            }
+
+            impl ::std::ops::Drop for #instance {
+                fn drop(&mut self) {
+                    // A test that is already failing should report its own panic rather than abort
+                    // on a second one.
+                    if !::std::thread::panicking() {
+                        Self::verify(self.0);
+                    }
+                    Self::forget(self.0);
+                }
+            }
+        }
+    } else {
+        quote! {
+            impl ::std::clone::Clone for #instance {
+                fn clone(&self) -> Self {
+                    *self
+                }
+            }
+
+            impl ::std::marker::Copy for #instance {}
+        }
+    };
+
+    quote! {
+        #[doc(hidden)]
+        #vis struct #instance(u64);
+
+        #[allow(dead_code)]
+        impl #instance {
+            pub fn new() -> Self {
+                Self(Self::next())
+            }
+
+            pub fn id(&self) -> u64 {
+                self.0
+            }
+
+            fn next() -> u64 {
+                static NEXT: ::std::sync::atomic::AtomicU64 = ::std::sync::atomic::AtomicU64::new(1);
+                NEXT.fetch_add(1, ::std::sync::atomic::Ordering::Relaxed)
+            }
+
+            /// The key of the state of the instance in the stores, which for the identifier 0 is
+            /// one of its own for each thread.
+            fn key(id: u64) -> u64 {
+                if id != 0 {
+                    return id;
+                }
+                ::std::thread_local! {
+                    static KEY: u64 = #instance::next();
+                }
+                KEY.with(|key| *key)
+            }
+        }
+
+        impl ::std::default::Default for #instance {
+            fn default() -> Self {
+                Self::new()
+            }
+        }
+
+        #stores
+
+        #clone
+
+        #comparisons
+    }
+}
+
+/// Generates the stores holding the calls of each instance of a mock, its deferred expectations
+/// and the closures configured to produce its return values, along with the function forgetting
+/// them once the instance is dropped.
+fn generate_stores(mock_name: &Ident) -> TokenStream {
+    let instance = instance_name(mock_name);
+    let deferred_type = deferred_name(mock_name);
+    let value = recorded_value();
+    let calls = generate_store(
+        &Ident::new("calls", Span::call_site()),
+        &quote! {
+            ::std::vec::Vec<(
+                &'static str,
+                ::std::vec::Vec<::std::string::String>,
+                ::std::vec::Vec<#value>,
+            )>
+        },
+    );
+    let deferred = generate_store(
+        &Ident::new("deferred", Span::call_site()),
+        &quote!(::std::vec::Vec<::std::sync::Arc<::std::sync::Mutex<#deferred_type>>>),
+    );
+    let returning = generate_store(
+        &Ident::new("returning", Span::call_site()),
+        &quote! {
+            ::std::collections::HashMap<
+                &'static str,
+                ::std::sync::Arc<dyn ::std::any::Any + ::std::marker::Send + ::std::marker::Sync>,
+            >
+        },
+    );
+
+    quote! {
+        #[allow(dead_code)]
+        impl #instance {
+            #calls
+
+            #deferred
+
+            #returning
+
+            fn forget(id: u64) {
+                Self::calls_store().remove(&Self::key(id));
+                Self::deferred_store().remove(&Self::key(id));
+                Self::returning_store().remove(&Self::key(id));
+            }
+        }
+    }
+}
+
+/// Generates the function giving access to the entry of an identifier in one of the stores of a
+/// mock, which are shared by all threads, along with the function locking the whole store.
+fn generate_store(name: &Ident, entry: &TokenStream) -> TokenStream {
+    let store = Ident::new(&format!("{name}_store"), Span::call_site());
+
+    quote! {
+        pub fn #name<R>(id: u64, f: impl FnOnce(&mut #entry) -> R) -> R {
+            f(Self::#store().entry(Self::key(id)).or_default())
+        }
+
+        fn #store() -> ::std::sync::MutexGuard<
+            'static,
+            ::std::collections::HashMap<u64, #entry>,
+        > {
+            static STORE: ::std::sync::LazyLock<
+                ::std::sync::Mutex<::std::collections::HashMap<u64, #entry>>,
+            > = ::std::sync::LazyLock::new(::std::default::Default::default);
+            // The stores are left consistent by a panicking test, which must not fail the others.
+            STORE
+                .lock()
+                .unwrap_or_else(::std::sync::PoisonError::into_inner)
+        }
+    }
+}
+
+/// The type of the recorded copy of an argument, which is shared by all threads along with the
+/// call log and left out for the arguments that cannot be copied.
+fn recorded_value() -> TokenStream {
+    quote! {
+        ::std::option::Option<
+            ::std::sync::Arc<dyn ::std::any::Any + ::std::marker::Send + ::std::marker::Sync>,
+        >
+    }
+}
+
+/// Generates `Debug`, which shows the identifier of the instance, and the comparisons and hash of
+/// the instance type, which ignore it.
+fn generate_comparisons(instance: &Ident) -> TokenStream {
+    quote! {
+        impl ::std::fmt::Debug for #instance {
+            fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+                f.debug_tuple(stringify!(#instance)).field(&self.0).finish()
+            }
+        }
+
+        impl ::std::cmp::PartialEq for #instance {
+            fn eq(&self, _: &Self) -> bool {
+                true
+            }
+        }
+
+        impl ::std::cmp::Eq for #instance {}
+
+        impl ::std::cmp::PartialOrd for #instance {
+            fn partial_cmp(&self, other: &Self) -> ::std::option::Option<::std::cmp::Ordering> {
+                ::std::option::Option::Some(self.cmp(other))
+            }
+        }
+
+        impl ::std::cmp::Ord for #instance {
+            fn cmp(&self, _: &Self) -> ::std::cmp::Ordering {
+                ::std::cmp::Ordering::Equal
+            }
+        }
+
+        impl ::std::hash::Hash for #instance {
+            fn hash<H: ::std::hash::Hasher>(&self, _: &mut H) {}
+        }
+    }
+}
+
+/// Generates the functions deferring the expectations of each instance of a mock, along with the
+/// function verifying those of an instance.
+fn generate_verification(mock_name: &Ident) -> TokenStream {
+    let deferred = deferred_name(mock_name);
+    let instance = instance_name(mock_name);
+    let value = recorded_value();
+
+    quote! {
+        #[allow(dead_code)]
+        impl #instance {
+            pub fn defer(deferred: ::std::sync::Arc<::std::sync::Mutex<#deferred>>) {
+                let id = #deferred::lock(&deferred).instance;
+                Self::deferred(id, |expectations| expectations.push(deferred));
+            }
+
+            pub fn sequenced(id: u64, method: &'static str, values: &[#value]) {
+                let values = #deferred::recorded(values);
+                Self::deferred(id, |expectations| {
+                    #deferred::sequenced(expectations, method, &values);
+                });
+            }
+
+            /// Verifies the deferred expectations of the instance, and forgets them.
+            #[track_caller]
+            pub fn verify(id: u64) {
+                let expectations = Self::deferred(id, ::std::mem::take);
+                let (failures, methods) = Self::calls(id, |calls| {
+                    let mut failures = ::std::string::String::new();
+                    let mut methods: ::std::vec::Vec<&'static str> = ::std::vec::Vec::new();
+                    for expectation in &expectations {
+                        let expectation = #deferred::lock(expectation);
+                        if let ::std::option::Option::Some(failure) = expectation.verify(calls) {
+                            failures.push_str(&format!("\n    {failure}"));
+                            for method in expectation.methods {
+                                if !methods.contains(method) {
+                                    methods.push(method);
+                                }
+                            }
+                        }
+                    }
+                    let methods = methods.iter().fold(
+                        ::std::string::String::new(),
+                        |mut report, method| {
+                            let count = calls.iter().filter(|(called, ..)| called == method).count();
+                            report.push_str(&format!("\n    {method}: {count} call(s)"));
+                            report
+                        },
+                    );
+                    (failures, methods)
+                });
+                assert!(
+                    failures.is_empty(),
+                    "unsatisfied expectations of `{}`:{}\n  calls of its methods:{}",
+                    stringify!(#mock_name),
+                    failures,
+                    methods,
+                );
+            }
+        }
+    }
+}
+
+/// Whether the type derives `Copy`.
+fn is_copy(attrs: &[Attribute]) -> bool {
+    attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("derive"))
+        .filter_map(|attr| {
+            attr.parse_args_with(Punctuated::<Path, Token![,]>::parse_terminated)
+                .ok()
+        })
+        .flatten()
+        .any(|path| {
+            path.segments
+                .last()
+                .is_some_and(|segment| segment.ident == "Copy")
+        })
+}
+
+/// Generates the expectation type returned by the `expect_<method>` functions, which holds the
+/// recorded calls of a method.
+fn generate_expectation(vis: &Visibility, mock_name: &Ident, tracking: &Tracking) -> TokenStream {
+    let expectation = expectation_name(mock_name);
+    let deferred = deferred_name(mock_name);
+    let cardinalities = generate_cardinalities(mock_name, &expectation);
+    let matching = generate_matching(mock_name, &expectation);
+    // Deferred expectations are verified when the mock is dropped, which only the mocks with an
+    // instance of their own get to see.
+    let deferring =
+        matches!(tracking, Tracking::Owned(_)).then(|| generate_deferring(mock_name, &expectation));
+    let value = recorded_value();
+
+    quote! {
+        /// The calls of a method, checked right away with `times` and the other assertions, or
+        /// once the mock is dropped for a `deferred` expectation. The type parameter describes
+        /// the arguments of the method, so that `with` knows what to match them against.
+        #[must_use]
+        #vis struct #expectation<S = ()> {
+            method: &'static str,
+            methods: &'static [&'static str],
+            instance: u64,
+            calls: ::std::vec::Vec<::std::string::String>,
+            arguments: ::std::vec::Vec<::std::vec::Vec<::std::string::String>>,
+            values: ::std::vec::Vec<
+                ::std::vec::Vec<#value>,
+            >,
+            rejected: ::std::vec::Vec<::std::string::String>,
+            recorded: usize,
+            matchers: ::std::vec::Vec<
+                ::std::boxed::Box<
+                    dyn Fn(
+                        &[::std::option::Option<&dyn ::std::any::Any>],
+                    ) -> ::std::option::Option<(usize, ::std::string::String)>
+                        + ::std::marker::Send,
+                >,
+            >,
+            deferred: ::std::option::Option<::std::sync::Arc<::std::sync::Mutex<#deferred>>>,
+            signature: ::std::marker::PhantomData<S>,
+        }
+
+        impl<S> #expectation<S> {
+            #[doc(hidden)]
+            pub fn new(
+                method: &'static str,
+                methods: &'static [&'static str],
+                instance: u64,
+                calls: ::std::vec::Vec<(
+                    ::std::vec::Vec<::std::string::String>,
+                    ::std::vec::Vec<#value>,
+                )>,
+            ) -> Self {
+                let (arguments, values): (::std::vec::Vec<_>, ::std::vec::Vec<_>) =
+                    calls.into_iter().unzip();
+                Self {
+                    method,
+                    methods,
+                    instance,
+                    calls: arguments.iter().map(|call| call.join(", ")).collect(),
+                    recorded: arguments.len(),
+                    arguments,
+                    values,
+                    rejected: ::std::vec::Vec::new(),
+                    matchers: ::std::vec::Vec::new(),
+                    deferred: ::std::option::Option::None,
+                    signature: ::std::marker::PhantomData,
+                }
+            }
+
+            /// The arguments of each recorded call, formatted with `Debug` when available.
+            pub fn calls(&self) -> &[::std::string::String] {
+                &self.calls
+            }
+        }
+
+        #cardinalities
+
+        #matching
+
+        #deferring
+    }
+}
+
+/// Generates the assertions on the number of calls of the expectation type, which are checked
+/// right away, or configure the expectation once it is deferred.
+fn generate_cardinalities(mock_name: &Ident, expectation: &Ident) -> TokenStream {
+    let deferred = deferred_name(mock_name);
+
+    quote! {
+        impl<S> #expectation<S> {
+            /// Asserts that the method has been called exactly `n` times.
+            #[track_caller]
+            pub fn times(self, n: usize) -> Self {
+                self.called(n, ::std::option::Option::Some(n))
+            }
+
+            /// Asserts that the method has been called at least `n` times.
+            #[track_caller]
+            pub fn at_least(self, n: usize) -> Self {
+                self.called(n, ::std::option::Option::None)
+            }
+
+            /// Asserts that the method has been called at most `n` times.
+            #[track_caller]
+            pub fn at_most(self, n: usize) -> Self {
+                self.called(0, ::std::option::Option::Some(n))
+            }
+
+            /// Asserts that the method has not been called.
+            #[track_caller]
+            pub fn never(self) -> Self {
+                self.times(0)
+            }
+
+            #[track_caller]
+            fn called(self, min: usize, max: ::std::option::Option<usize>) -> Self {
+                if let ::std::option::Option::Some(deferred) = &self.deferred {
+                    #deferred::lock(deferred).bounds = (min, max);
+                    return self;
+                }
+                let count = self.calls.len();
+                assert!(
+                    count >= min && max.is_none_or(|max| count <= max),
+                    "expected `{}::{}` to be called {}, but it was called {} time(s){}{}",
+                    stringify!(#mock_name),
+                    self.method,
+                    #deferred::cardinality(min, max),
+                    count,
+                    self.calls
+                        .iter()
+                        .enumerate()
+                        .fold(::std::string::String::new(), |mut report, (i, call)| {
+                            report.push_str(&format!("\n    {}: {}({})", i + 1, self.method, call));
+                            report
+                        }),
+                    #deferred::rejected_report(&self.rejected),
+                );
+                self
+            }
+        }
+    }
+}
+
+/// Generates the `with` function of the expectation type, which narrows the calls down to those
+/// with matching arguments.
+fn generate_matching(mock_name: &Ident, expectation: &Ident) -> TokenStream {
+    let deferred = deferred_name(mock_name);
+
+    quote! {
+        impl<S> #expectation<S> {
+            /// Keeps only the calls of which the arguments match, as given by `matchers::args`.
+            /// The calls that are left out are reported along with the argument that did not
+            /// match when a later assertion fails.
+            pub fn with<M>(mut self, matcher: M) -> Self
+            where
+                M: Fn(
+                        ::std::marker::PhantomData<S>,
+                        &[::std::option::Option<&dyn ::std::any::Any>],
+                    ) -> ::std::option::Option<(usize, ::std::string::String)>
+                    + ::std::marker::Send
+                    + 'static,
+            {
+                let matcher = ::std::boxed::Box::new(
+                    move |values: &[::std::option::Option<&dyn ::std::any::Any>]| {
+                        matcher(::std::marker::PhantomData, values)
+                    },
+                );
+                if let ::std::option::Option::Some(deferred) = &self.deferred {
+                    #deferred::lock(deferred).matchers.push(matcher);
+                    return self;
+                }
+
+                let mut kept = (
+                    ::std::vec::Vec::new(),
+                    ::std::vec::Vec::new(),
+                    ::std::vec::Vec::new(),
+                );
+                let calls = self
+                    .calls
+                    .drain(..)
+                    .zip(self.arguments.drain(..))
+                    .zip(self.values.drain(..));
+                for ((call, arguments), values) in calls {
+                    let matched = #deferred::recorded(&values);
+                    match matcher(&matched) {
+                        ::std::option::Option::Some((position, expected)) => {
+                            self.rejected.push(format!(
+                                "{}({}): argument {} was `{}`, expected {}",
+                                self.method,
+                                call,
+                                position + 1,
+                                arguments[position],
+                                expected,
+                            ));
+                        }
+                        ::std::option::Option::None => {
+                            kept.0.push(call);
+                            kept.1.push(arguments);
+                            kept.2.push(values);
+                        }
+                    }
+                }
+                (self.calls, self.arguments, self.values) = kept;
+                self.matchers.push(matcher);
+                self
+            }
+        }
+    }
+}
+
+/// Generates the functions of the expectation type that turn it into a deferred expectation,
+/// which is verified against the calls made after it once the mock is dropped or checked.
+fn generate_deferring(mock_name: &Ident, expectation: &Ident) -> TokenStream {
+    let deferred = deferred_name(mock_name);
+    let instance = instance_name(mock_name);
+
+    quote! {
+        impl<S> #expectation<S> {
+            /// Turns the expectation into one verified when the mock is dropped or through
+            /// `checkpoint`, against the calls made from now on. It expects at least one call
+            /// unless told otherwise by `times`, `at_least`, `at_most` or `never`, and keeps the
+            /// matchers given through `with`.
+            #[track_caller]
+            pub fn deferred(mut self) -> Self {
+                if self.deferred.is_some() {
+                    return self;
+                }
+                let deferred = ::std::sync::Arc::new(::std::sync::Mutex::new(#deferred {
+                    method: self.method,
+                    methods: self.methods,
+                    instance: self.instance,
+                    start: self.recorded,
+                    assigned: 0,
+                    bounds: (1, ::std::option::Option::None),
+                    matchers: ::std::mem::take(&mut self.matchers),
+                    sequence: ::std::option::Option::None,
+                    location: ::std::panic::Location::caller(),
+                }));
+                #instance::defer(::std::sync::Arc::clone(&deferred));
+                self.calls.clear();
+                self.arguments.clear();
+                self.values.clear();
+                self.rejected.clear();
+                self.deferred = ::std::option::Option::Some(deferred);
+                self
+            }
+
+            /// Defers the expectation and adds it to a `matchers::Sequence`, so that the calls
+            /// it matches have to come after those of the expectations added before it, on this
+            /// mock or any other.
+            #[track_caller]
+            pub fn in_sequence<Q>(self, sequence: &Q) -> Self
+            where
+                Q: ::std::convert::AsRef<
+                    ::std::sync::Arc<
+                        ::std::sync::Mutex<(
+                            ::std::vec::Vec<::std::string::String>,
+                            ::std::vec::Vec<usize>,
+                        )>,
+                    >,
+                >,
+            {
+                let this = self.deferred();
+                let sequence = ::std::sync::Arc::clone(sequence.as_ref());
+                let position = {
+                    let mut sequence = #deferred::lock(&sequence);
+                    sequence
+                        .0
+                        .push(format!("{}::{}", stringify!(#mock_name), this.method));
+                    sequence.0.len() - 1
+                };
+                if let ::std::option::Option::Some(deferred) = &this.deferred {
+                    #deferred::lock(deferred).sequence =
+                        ::std::option::Option::Some((sequence, position));
+                }
+                this
+            }
+        }
+    }
+}
+
+/// Generates the type of the deferred expectations, which verifies them against the call log.
+fn generate_deferred(vis: &Visibility, mock_name: &Ident) -> TokenStream {
+    let deferred = deferred_name(mock_name);
+    let reports = generate_reports(&deferred);
+    let sequencing = generate_sequencing(&deferred);
+    let access = generate_access(&deferred);
+    let value = recorded_value();
+
+    quote! {
+        #[doc(hidden)]
+        #vis struct #deferred {
+            method: &'static str,
+            methods: &'static [&'static str],
+            instance: u64,
+            start: usize,
+            assigned: usize,
+            bounds: (usize, ::std::option::Option<usize>),
+            matchers: ::std::vec::Vec<
+                ::std::boxed::Box<
+                    dyn Fn(
+                        &[::std::option::Option<&dyn ::std::any::Any>],
+                    ) -> ::std::option::Option<(usize, ::std::string::String)>
+                        + ::std::marker::Send,
+                >,
+            >,
+            sequence: ::std::option::Option<(
+                ::std::sync::Arc<
+                    ::std::sync::Mutex<(
+                        ::std::vec::Vec<::std::string::String>,
+                        ::std::vec::Vec<usize>,
+                    )>,
+                >,
+                usize,
+            )>,
+            location: &'static ::std::panic::Location<'static>,
+        }
+
+        #reports
+
+        #sequencing
+
+        #access
+
+        impl #deferred {
+            /// The position of the first argument that a matcher rejects, along with what was
+            /// expected of it.
+            fn mismatch(
+                &self,
+                values: &[::std::option::Option<&dyn ::std::any::Any>],
+            ) -> ::std::option::Option<(usize, ::std::string::String)> {
+                self.matchers.iter().find_map(|matcher| matcher(values))
+            }
+
+            /// Describes how the expectation is unsatisfied by the calls, if it is. An expectation
+            /// in a sequence only counts the calls that were assigned to it.
+            fn verify(
+                &self,
+                calls: &[(
+                    &'static str,
+                    ::std::vec::Vec<::std::string::String>,
+                    ::std::vec::Vec<#value>,
+                )],
+            ) -> ::std::option::Option<::std::string::String> {
+                let mut rejected = ::std::vec::Vec::new();
+                let count = calls
+                    .iter()
+                    .filter(|(called, ..)| *called == self.method)
+                    .skip(self.start)
+                    .filter(|(_, arguments, values)| {
+                        let values = Self::recorded(values);
+                        let ::std::option::Option::Some((position, expected)) =
+                            self.mismatch(&values)
+                        else {
+                            return true;
+                        };
+                        rejected.push(format!(
+                            "{}({}): argument {} was `{}`, expected {}",
+                            self.method,
+                            arguments.join(", "),
+                            position + 1,
+                            arguments[position],
+                            expected,
+                        ));
+                        false
+                    })
+                    .count();
+                let count = if self.sequence.is_some() {
+                    self.assigned
+                } else {
+                    count
+                };
+
+                let (min, max) = self.bounds;
+                if count < min || max.is_some_and(|max| count > max) {
+                    return ::std::option::Option::Some(format!(
+                        "`{}` was expected to be called {}, but it was called {} time(s) (configured at {}){}",
+                        self.method,
+                        Self::cardinality(min, max),
+                        count,
+                        self.location,
+                        Self::rejected_report(&rejected),
+                    ));
+                }
+
+                self.out_of_sequence()
+            }
+        }
+    }
+}
+
+/// Generates the functions locking the deferred expectations and their sequences, and handing
+/// the recorded arguments to the matchers, which are shared by the expectations and the stores.
+fn generate_access(deferred: &Ident) -> TokenStream {
+    let value = recorded_value();
+
+    quote! {
+        impl #deferred {
+            /// Locks the expectation, or its sequence, which stays consistent when a test panics
+            /// while holding it.
+            fn lock<T>(mutex: &::std::sync::Mutex<T>) -> ::std::sync::MutexGuard<'_, T> {
+                mutex.lock().unwrap_or_else(::std::sync::PoisonError::into_inner)
+            }
+
+            /// The recorded arguments of a call, as given to the matchers.
+            fn recorded(
+                values: &[#value],
+            ) -> ::std::vec::Vec<::std::option::Option<&dyn ::std::any::Any>> {
+                values
+                    .iter()
+                    .map(|value| value.as_deref().map(|value| value as &dyn ::std::any::Any))
+                    .collect()
+            }
+        }
+    }
+}
+
+/// Generates the functions describing the expected number of calls and the calls that did not
+/// match, which are shared by the immediate and deferred expectations.
+fn generate_reports(deferred: &Ident) -> TokenStream {
+    quote! {
+        impl #deferred {
+            fn cardinality(min: usize, max: ::std::option::Option<usize>) -> ::std::string::String {
+                match max {
+                    ::std::option::Option::Some(max) if max == min => format!("{min} time(s)"),
+                    ::std::option::Option::Some(max) if min == 0 => format!("at most {max} time(s)"),
+                    ::std::option::Option::Some(max) => format!("{min} to {max} time(s)"),
+                    ::std::option::Option::None => format!("at least {min} time(s)"),
+                }
+            }
+
+            fn rejected_report(rejected: &[::std::string::String]) -> ::std::string::String {
+                if rejected.is_empty() {
+                    return ::std::string::String::new();
+                }
+                rejected.iter().fold(
+                    ::std::string::String::from("\n  calls that did not match:"),
+                    |mut report, rejected| {
+                        report.push_str(&format!("\n    {rejected}"));
+                        report
+                    },
+                )
+            }
+        }
+    }
+}
+
+/// Generates the functions of the deferred expectations that log their calls in their sequence
+/// and check the order of the sequence.
+fn generate_sequencing(deferred: &Ident) -> TokenStream {
+    let assignment = generate_assignment(deferred);
+
+    quote! {
+        #assignment
+
+        impl #deferred {
+            /// The position of the expectation in its sequence.
+            fn position(&self) -> ::std::option::Option<usize> {
+                self.sequence.as_ref().map(|(_, position)| *position)
+            }
+
+            /// Whether the expectation allows more calls than it was assigned in its sequence.
+            fn takes_more(&self) -> bool {
+                self.bounds.1.is_none_or(|max| self.assigned < max)
+            }
+
+            /// Describes the call that came out of order in the sequence of the expectation, if
+            /// there is one.
+            fn out_of_sequence(&self) -> ::std::option::Option<::std::string::String> {
+                let (sequence, position) = self.sequence.as_ref()?;
+                let sequence = Self::lock(sequence);
+                let (names, log) = &*sequence;
+                let mut reached = 0;
+                for &called in log {
+                    if called == *position && reached > called {
+                        return ::std::option::Option::Some(format!(
+                            "`{}` was called after `{}`, which comes later in its sequence (configured at {})",
+                            names[called],
+                            names[reached],
+                            self.location,
+                        ));
+                    }
+                    reached = reached.max(called);
+                }
+                ::std::option::Option::None
+            }
+        }
+    }
+}
+
+/// Generates the function of the deferred expectations that assigns each call to one expectation
+/// of the sequences it belongs to.
+fn generate_assignment(deferred: &Ident) -> TokenStream {
+    quote! {
+        impl #deferred {
+            /// Logs a call in the sequences of the expectations it matches. In each sequence, the
+            /// call is assigned to the first of them still short of its number of calls, or else
+            /// to the last one that the sequence reached and that takes more calls, so that a
+            /// method may come back later in the sequence.
+            fn sequenced(
+                expectations: &[::std::sync::Arc<::std::sync::Mutex<Self>>],
+                method: &'static str,
+                values: &[::std::option::Option<&dyn ::std::any::Any>],
+            ) {
+                let mut matching: ::std::vec::Vec<_> = expectations
+                    .iter()
+                    .filter(|expectation| {
+                        let expectation = Self::lock(expectation);
+                        expectation.sequence.is_some()
+                            && expectation.method == method
+                            && expectation.mismatch(values).is_none()
+                    })
+                    .collect();
+                matching.sort_by_key(|expectation| Self::lock(expectation).position());
+
+                while let ::std::option::Option::Some(first) = matching.first() {
+                    let ::std::option::Option::Some((sequence, _)) =
+                        Self::lock(first).sequence.clone()
+                    else {
+                        break;
+                    };
+                    let (candidates, rest): (::std::vec::Vec<_>, ::std::vec::Vec<_>) =
+                        matching.into_iter().partition(|expectation| {
+                            Self::lock(expectation)
+                                .sequence
+                                .as_ref()
+                                .is_some_and(|(other, _)| ::std::sync::Arc::ptr_eq(other, &sequence))
+                        });
+                    matching = rest;
+
+                    let reached = Self::lock(&sequence).1.iter().max().copied();
+                    let assigned = candidates
+                        .iter()
+                        .find(|expectation| {
+                            let expectation = Self::lock(expectation);
+                            expectation.assigned < expectation.bounds.0
+                        })
+                        .or_else(|| {
+                            candidates.iter().rev().find(|expectation| {
+                                let expectation = Self::lock(expectation);
+                                expectation.position() <= reached && expectation.takes_more()
+                            })
+                        })
+                        .or_else(|| candidates.iter().find(|expectation| Self::lock(expectation).takes_more()))
+                        .or(candidates.last());
+                    if let ::std::option::Option::Some(assigned) = assigned {
+                        let mut assigned = Self::lock(assigned);
+                        assigned.assigned += 1;
+                        if let ::std::option::Option::Some(position) = assigned.position() {
+                            Self::lock(&sequence).1.push(position);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Binds every argument of the function to an identifier so that it can be recorded or passed on,
+/// and returns the identifiers in order.
+pub fn bind_arguments(sig: &mut Signature) -> Vec<Ident> {
+    sig.inputs
+        .iter_mut()
+        .enumerate()
+        .filter_map(|(i, arg)| match arg {
+            FnArg::Typed(typ) => Some(bind_argument(&mut typ.pat, i)),
+            FnArg::Receiver(_) => None,
+        })
+        .collect()
+}
+
+/// The types of the arguments of the function, excluding the receiver.
+pub fn argument_types(sig: &Signature) -> Vec<&Type> {
+    sig.inputs
+        .iter()
+        .filter_map(|arg| match arg {
+            FnArg::Typed(typ) => Some(&*typ.ty),
+            FnArg::Receiver(_) => None,
+        })
+        .collect()
+}
+
+/// Generates the statements that add the call to the log of the instance of the mock, or of its
+/// type for functions without a receiver. Each argument is kept both formatted with `Debug`, or
+/// its type name, and as an owned copy when it can be cloned and sent to other threads and its type
+/// is known to own its data, which is what matchers are checked against.
+pub fn record_call(
+    sig: &Signature,
+    method: &str,
+    arguments: &[Ident],
+    generics_in_scope: &Generics,
+) -> TokenStream {
+    let id = instance_id(sig);
+    // `Self` may borrow when the mock has lifetime parameters.
+    let self_ = Ident::new("Self", Span::call_site());
+    let parameters: Vec<&Ident> = sig
+        .generics
+        .type_params()
+        .chain(generics_in_scope.type_params())
+        .map(|param| &param.ident)
+        .chain(generics_in_scope.lifetimes().next().map(|_| &self_))
+        .collect();
+    let captured: Vec<Option<TokenStream>> = argument_types(sig)
+        .into_iter()
+        .zip(arguments)
+        .map(|(ty, argument)| match ty {
+            Type::Reference(reference) => owns_its_data(&reference.elem, &parameters)
+                .then(|| quote!((&Capture(&*#argument)).capture())),
+            ty => owns_its_data(ty, &parameters).then(|| quote!((&Capture(&#argument)).capture())),
+        })
+        .collect();
+    let capture = captured.iter().any(Option::is_some).then(generate_capture);
+    let captured = captured
+        .into_iter()
+        .map(|captured| captured.unwrap_or_else(|| quote!(::std::option::Option::None)));
+
+    quote! {
+        let call = {
+            struct Describe<'a, T: ?Sized>(&'a T);
+
+            trait ViaDebug {
+                fn describe(&self) -> ::std::string::String;
+            }
+
+            impl<T: ::std::fmt::Debug + ?Sized> ViaDebug for Describe<'_, T> {
+                fn describe(&self) -> ::std::string::String {
+                    format!("{:?}", self.0)
+                }
+            }
+
+            trait ViaTypeName {
+                fn describe(&self) -> ::std::string::String;
+            }
+
+            impl<T: ?Sized> ViaTypeName for &Describe<'_, T> {
+                fn describe(&self) -> ::std::string::String {
+                    format!("<{}>", ::std::any::type_name::<T>())
+                }
+            }
+
+            ::std::vec![#((&Describe(&#arguments)).describe()),*]
+        };
+        let values = {
+            #capture
+
+            ::std::vec![#(#captured),*]
+        };
+        Self::__mocktoffel_sequenced(#id, #method, &values);
+        Self::__mocktoffel_calls(#id, |calls| calls.push((#method, call, values)));
+    }
+}
+
+/// Generates the `Capture` wrapper, which takes an owned copy of the arguments that can be cloned
+/// and sent to other threads, and leaves out the others.
+fn generate_capture() -> TokenStream {
+    let value = recorded_value();
+
+    quote! {
+        struct Capture<'a, T: ?Sized>(&'a T);
+
+        trait ViaToOwned {
+            fn capture(&self) -> #value;
+        }
+
+        impl<T: ::std::borrow::ToOwned + ?Sized> ViaToOwned for Capture<'_, T>
+        where
+            T::Owned: ::std::marker::Send + ::std::marker::Sync + 'static,
+        {
+            fn capture(&self) -> #value {
+                ::std::option::Option::Some(::std::sync::Arc::new(self.0.to_owned()))
+            }
+        }
+
+        trait ViaNothing {
+            fn capture(&self) -> #value;
+        }
+
+        impl<T: ?Sized> ViaNothing for &Capture<'_, T> {
+            fn capture(&self) -> #value {
+                ::std::option::Option::None
+            }
+        }
+    }
+}
+
+/// The types of the standard library that hold a lifetime, which may be left out when they are
+/// named, as in `Cow<str>` for `Cow<'_, str>`.
+const BORROWING: &[&str] = &[
+    "Arguments",
+    "BorrowedFd",
+    "CharIndices",
+    "Chars",
+    "Context",
+    "Cow",
+    "Drain",
+    "Formatter",
+    "IoSlice",
+    "IoSliceMut",
+    "Iter",
+    "IterMut",
+    "Location",
+    "MutexGuard",
+    "PanicHookInfo",
+    "Ref",
+    "RefMut",
+    "RwLockReadGuard",
+    "RwLockWriteGuard",
+    "StderrLock",
+    "StdinLock",
+    "StdoutLock",
+];
+
+/// Whether the type can be seen to own its data, so that its owned copy is `'static` and can be
+/// recorded. The copy is picked by the trait system, which does not look at lifetimes, so types
+/// naming a lifetime other than `'static`, holding a reference, one of the `parameters` or one of
+/// the `BORROWING` types, which may borrow, are not recorded rather than failing to compile.
+/// Lifetimes hidden in the path of another type, as in `Parser` for `Parser<'_>`, cannot be seen
+/// and have to be written out.
+fn owns_its_data(ty: &Type, parameters: &[&Ident]) -> bool {
+    match ty {
+        Type::Array(array) => owns_its_data(&array.elem, parameters),
+        Type::Group(group) => owns_its_data(&group.elem, parameters),
+        Type::Paren(paren) => owns_its_data(&paren.elem, parameters),
+        Type::Ptr(pointer) => owns_its_data(&pointer.elem, parameters),
+        Type::Slice(slice) => owns_its_data(&slice.elem, parameters),
+        Type::Tuple(tuple) => tuple
+            .elems
+            .iter()
+            .all(|elem| owns_its_data(elem, parameters)),
+        Type::Never(_) => true,
+        Type::Path(path) => {
+            path.qself.is_none()
+                && !path
+                    .path
+                    .get_ident()
+                    .is_some_and(|ident| parameters.contains(&ident))
+                && path
+                    .path
+                    .segments
+                    .iter()
+                    .all(|segment| segment_owns_its_data(segment, parameters))
+        }
+        _ => false,
+    }
+}
+
+/// Whether the segment of a path can be seen to own its data, given that the `BORROWING` types
+/// only do when their lifetime is written out as `'static`.
+fn segment_owns_its_data(segment: &PathSegment, parameters: &[&Ident]) -> bool {
+    let hides_lifetime = BORROWING.contains(&segment.ident.to_string().as_str());
+    match &segment.arguments {
+        PathArguments::None => !hides_lifetime,
+        PathArguments::AngleBracketed(arguments) => {
+            (!hides_lifetime
+                || arguments
+                    .args
+                    .iter()
+                    .any(|argument| matches!(argument, GenericArgument::Lifetime(_))))
+                && arguments.args.iter().all(|argument| match argument {
+                    GenericArgument::Lifetime(lifetime) => lifetime.ident == "static",
+                    GenericArgument::Type(ty) => owns_its_data(ty, parameters),
+                    GenericArgument::AssocType(assoc) => owns_its_data(&assoc.ty, parameters),
+                    GenericArgument::Const(_) => true,
+                    _ => false,
+                })
+        }
+        PathArguments::Parenthesized(_) => false,
+    }
+}
+
+/// The identifier of the mock instance that the calls of the function belong to, which is 0 for
+/// the functions without a receiver.
+pub fn instance_id(sig: &Signature) -> TokenStream {
+    if sig.receiver().is_some() {
+        quote!(self.__mocktoffel_id())
+    } else {
+        quote!(0)
+    }
+}
+
+/// The marker type of the arguments of a method, as in `fn(&i64, &str)`, which lets matchers find
+/// the type each recorded argument was copied to. Borrowed arguments are recorded as their owned
+/// counterpart, so the reference is left out. Arguments that may borrow are not recorded, so they
+/// stand as `dyn Any`, which only `any` matches.
+pub fn argument_signature(sig: &Signature) -> TokenStream {
+    let inputs = argument_types(sig).into_iter().map(|ty| {
+        let ty = match ty {
+            Type::Reference(reference) => &*reference.elem,
+            ty => ty,
+        };
+        if owns_its_data(ty, &[]) {
+            quote!(#ty)
+        } else {
+            quote!((dyn ::std::any::Any + 'static))
+        }
+    });
+    quote!(fn(#(&#inputs),*))
+}
+
+/// Generates the statements that hand the call over to the closure installed through
+/// `returning_<method>`, if there is one. The closure is locked while it runs, so that the calls
+/// made from several threads take their turn.
+pub fn returning_call(
+    sig: &Signature,
+    method: &str,
+    arguments: &[Ident],
+    output: Option<&TokenStream>,
+) -> TokenStream {
+    let id = instance_id(sig);
+    let inputs = argument_types(sig);
+    let output = output.map(|output| quote!(-> #output));
+
+    quote! {
+        if let ::std::option::Option::Some(returning) =
+            Self::__mocktoffel_returning(#id, |returning| returning.get(#method).cloned())
+        {
+            let returning = returning
+                .downcast::<::std::sync::Mutex<
+                    ::std::boxed::Box<dyn FnMut((#(#inputs,)*)) #output + ::std::marker::Send>,
+                >>()
+                .unwrap_or_else(|_| {
+                    ::std::panic!("`{}` was configured with another signature", #method)
+                });
+            let mut returning = returning
+                .lock()
+                .unwrap_or_else(::std::sync::PoisonError::into_inner);
+            return returning((#(#arguments,)*));
+        }
+    }
+}
+
+fn bind_argument(pat: &mut Pat, position: usize) -> Ident {
+    if let Pat::Ident(PatIdent {
+        ident,
+        subpat: None,
+        ..
+    }) = pat
+    {
+        ident.clone()
+    } else {
+        let ident = Ident::new(&format!("__arg{position}"), Span::call_site());
+        *pat = parse_quote!(#ident);
+        ident
+    }
+}
@@ -1,107 +1,240 @@
-use crate::branch::get_mocking_candidate;
+use crate::branch::{get_mocking_candidate, mock_tuple};
+use crate::diagnostics::unsupported;
+use crate::extract::{MockedField, mocked_field};
+use crate::options::{Naming, Options};
 use proc_macro::TokenStream;
-use quote::quote;
-use syn::{Field, Fields, ItemEnum, ItemStruct};
+use quote::{format_ident, quote};
+use syn::punctuated::Punctuated;
+use syn::{
+    Attribute, Field, Ident, Item, ItemEnum, ItemStruct, ItemUnion, Meta, Token, Type, Visibility,
+    parse_quote,
+};
 
 pub trait Toffelise {
-    fn replace_mocks(self) -> TokenStream;
+    fn replace_mocks(self, naming: &Naming) -> TokenStream;
 }
 
 impl Toffelise for ItemStruct {
-    fn replace_mocks(self) -> TokenStream {
-        let struct_name = self.ident;
-        let generics = self.generics;
-        let attributes = self.attrs;
-
-        match self.fields {
-            Fields::Named(named) => {
-                let fields: Vec<Field> = named
-                    .named
-                    .into_iter()
-                    .map(|mut field| {
-                        if field
-                            .attrs
-                            .iter()
-                            .any(|attr| attr.meta.path().is_ident("mocked"))
-                        {
-                            field.attrs = vec![];
-                            field.ty = get_mocking_candidate(&field.ty).mocked_type;
-                        }
-                        field
-                    })
-                    .collect();
-
-                TokenStream::from(quote! {
-                    #(#attributes)*
-                       struct #struct_name #generics {
-                             #(#fields),*
-                       }
-                })
-            }
+    fn replace_mocks(mut self, naming: &Naming) -> TokenStream {
+        let mut streams = vec![];
+        for (i, field) in self.fields.iter_mut().enumerate() {
+            let vis = field.vis.clone();
+            replace_mocked_field(field, &self.ident, &vis, i, naming, &mut streams);
+        }
 
-            Fields::Unnamed(un) => {
-                let fields: Vec<Field> = un
-                    .unnamed
-                    .into_iter()
-                    .map(|mut field| {
-                        if field
-                            .attrs
-                            .iter()
-                            .any(|attr| attr.meta.path().is_ident("mocked"))
-                        {
-                            field.attrs = vec![];
-                            field.ty = get_mocking_candidate(&field.ty).mocked_type;
-                        }
-                        field
-                    })
-                    .collect();
-
-                TokenStream::from(quote! {
-                    #(#attributes)*
-                   struct #struct_name #generics(
-                         #(#fields),*
-                    );
-
-                })
-            }
-            Fields::Unit => unreachable!(),
+        TokenStream::from(quote! {
+            #self
+
+            #(#streams)*
+        })
+    }
+}
+
+impl Toffelise for ItemUnion {
+    fn replace_mocks(mut self, naming: &Naming) -> TokenStream {
+        let mut streams = vec![];
+        for (i, field) in self.fields.named.iter_mut().enumerate() {
+            let vis = field.vis.clone();
+            replace_mocked_field(field, &self.ident, &vis, i, naming, &mut streams);
         }
+
+        TokenStream::from(quote! {
+            #self
+
+            #(#streams)*
+        })
     }
 }
 
 impl Toffelise for ItemEnum {
-    fn replace_mocks(self) -> TokenStream {
-        let enum_name = self.ident;
-        let generics = self.generics;
-        let attributes = self.attrs;
-
-        let variants = self
-            .variants
-            .into_iter()
-            .fold(vec![], |mut var, mut variant| {
-                match variant.fields {
-                    Fields::Unnamed(ref mut un) => un.unnamed.iter_mut().for_each(|f| {
-                        if f.attrs
-                            .iter()
-                            .any(|attr| attr.meta.path().is_ident("mocked"))
-                        {
-                            f.ty = get_mocking_candidate(&f.ty).mocked_type;
-                            f.attrs = vec![];
-                        }
-                    }),
-                    Fields::Unit => {}
-                    Fields::Named(_) => unreachable!(),
-                }
-
-                var.push(variant);
-                var
-            });
+    fn replace_mocks(mut self, naming: &Naming) -> TokenStream {
+        let mut streams = vec![];
+        for variant in &mut self.variants {
+            let owner = format_ident!("{}{}", self.ident, variant.ident);
+            for (i, field) in variant.fields.iter_mut().enumerate() {
+                replace_mocked_field(field, &owner, &self.vis, i, naming, &mut streams);
+            }
+        }
 
         TokenStream::from(quote! {
-           #(#attributes)*
-            enum #enum_name #generics {
-                #(#variants),*
+            #self
+
+            #(#streams)*
+        })
+    }
+}
+
+/// Replaces the type of the field with its mock if it is marked with `#[mocked]`, collecting the
+/// code generated alongside the mock. For tuples, `#[mocked(0, 2)]` limits the mocking to the
+/// elements at those positions, and `#[mocked(with = Type)]` replaces the type with the given one.
+/// With `#[mocked(foreign)]`, the mocks are looked up in the current scope. The mocks generated
+/// for the field are given the visibility `vis` of the field.
+fn replace_mocked_field(
+    field: &mut Field,
+    owner: &Ident,
+    vis: &Visibility,
+    index: usize,
+    naming: &Naming,
+    streams: &mut Vec<proc_macro2::TokenStream>,
+) {
+    let Some(attr) = field
+        .attrs
+        .iter()
+        .find(|attr| attr.meta.path().is_ident("mocked"))
+        .cloned()
+    else {
+        return;
+    };
+    field
+        .attrs
+        .retain(|attr| !attr.meta.path().is_ident("mocked"));
+    let mocked = match mocked_field(&attr) {
+        Ok(mocked) => mocked,
+        Err(error) => {
+            streams.push(error.to_compile_error());
+            return;
+        }
+    };
+
+    let name = field_mock_name(owner, field, index);
+    let naming = &naming.visible_as(vis);
+    let prepared = match (&field.ty, mocked) {
+        (_, MockedField::With(ty)) => {
+            field.ty = *ty;
+            return;
+        }
+        (_, MockedField::Whole) => get_mocking_candidate(&field.ty, &name, naming),
+        (_, MockedField::Foreign) => get_mocking_candidate(&field.ty, &name, &naming.in_scope()),
+        (Type::Tuple(tuple), MockedField::Elements(elements)) => {
+            if let Some(out_of_range) = elements.iter().find(|i| **i >= tuple.elems.len()) {
+                streams.push(
+                    unsupported(
+                        &attr,
+                        format!(
+                            "the tuple has {} elements, there is no element {out_of_range}",
+                            tuple.elems.len()
+                        ),
+                    )
+                    .to_compile_error(),
+                );
+                return;
             }
+            mock_tuple(tuple, &name, Some(&elements), naming)
+        }
+        (_, MockedField::Elements(_)) => {
+            streams.push(
+                unsupported(
+                    &attr,
+                    "element positions can only be given for tuple fields",
+                )
+                .to_compile_error(),
+            );
+            return;
+        }
+    };
+
+    field.ty = prepared.mocked_type;
+    streams.extend(prepared.stream);
+}
+
+/// Names the mocks generated for a field after its owner and the field, so that the `callback`
+/// field of `Foo` gets a `FooCallback` prefix and the first field of a tuple struct `Foo` gets
+/// `Foo0`.
+fn field_mock_name(owner: &Ident, field: &Field, index: usize) -> Ident {
+    let Some(ident) = &field.ident else {
+        return format_ident!("{owner}{index}");
+    };
+    let camel_case: String = ident
+        .to_string()
+        .trim_start_matches("r#")
+        .split('_')
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            chars
+                .next()
+                .map(|first| first.to_uppercase().chain(chars).collect::<String>())
+                .unwrap_or_default()
         })
+        .collect();
+    format_ident!("{owner}{camel_case}")
+}
+
+/// The item as it is written, without the `#[mocked]` attributes on its fields.
+pub fn without_mocked_attributes(item: &Item) -> Item {
+    let mut item = item.clone();
+    let fields: Box<dyn Iterator<Item = &mut Field>> = match &mut item {
+        Item::Struct(s) => Box::new(s.fields.iter_mut()),
+        Item::Enum(e) => Box::new(
+            e.variants
+                .iter_mut()
+                .flat_map(|variant| variant.fields.iter_mut()),
+        ),
+        Item::Union(u) => Box::new(u.fields.named.iter_mut()),
+        _ => Box::new(std::iter::empty()),
+    };
+    for field in fields {
+        field
+            .attrs
+            .retain(|attr| !attr.meta.path().is_ident("mocked"));
+    }
+    item
+}
+
+/// Makes the `#[toffel]` of a mock replace the `#[mocked]` fields in every build where the mock
+/// exists, so that a mock holds the mocks of those fields even where its original type is not
+/// replaced, and the two are converted into each other with `#[mock(convert)]`.
+pub fn ungate(attrs: &mut [Attribute]) {
+    for attr in attrs {
+        if attr
+            .path()
+            .segments
+            .last()
+            .is_none_or(|segment| segment.ident != "toffel")
+        {
+            continue;
+        }
+        let path = attr.path().clone();
+        let options = match &attr.meta {
+            Meta::List(_) => attr
+                .parse_args_with(Punctuated::<Meta, Token![,]>::parse_terminated)
+                .unwrap_or_default(),
+            _ => Punctuated::new(),
+        };
+        let options = options
+            .into_iter()
+            .filter(|option| !option.path().is_ident("cfg") && !option.path().is_ident("always"));
+        *attr = parse_quote!(#[#path(always #(, #options)*)]);
     }
 }
+
+/// The type that a `#[toffel]` among `attrs` gives to the field in the mock, which is the mock of
+/// its type when the field is marked with `#[mocked]`, and its own type otherwise. `owner` and
+/// `index` are those the `toffel` names the mocks generated for the field after.
+pub fn replaced_type(attrs: &[Attribute], field: &Field, owner: &Ident, index: usize) -> Type {
+    let Some(attr) = attrs.iter().find(|attr| {
+        attr.path()
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "toffel")
+    }) else {
+        return field.ty.clone();
+    };
+    let mut options = Options::default();
+    if let Meta::List(_) = attr.meta {
+        // Invalid options are reported by the `toffel` itself.
+        let _ = attr.parse_nested_meta(|meta| options.parse(&meta));
+    }
+
+    let mut field = field.clone();
+    let vis = field.vis.clone();
+    replace_mocked_field(
+        &mut field,
+        owner,
+        &vis,
+        index,
+        options.naming(),
+        &mut Vec::new(),
+    );
+    field.ty
+}
@@ -0,0 +1,187 @@
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{
+    Block, GenericParam, Generics, ImplItemFn, ItemTrait, Path, TraitItem, Type, Visibility,
+    parse_quote, spanned::Spanned, token::Brace,
+};
+
+use crate::extract::take_mocked_with;
+use crate::options::Naming;
+use crate::pimpl::MockContext;
+use crate::record::{Tracking, generate_mock_state, instance_name};
+
+/// Generates a `<Trait>Mock` type that implements the trait. The mock implements the trait at
+/// `remote` instead when it is given, as for the traits of other crates restated in
+/// `mock_foreign!`.
+///
+/// Every method of the trait, including the ones with a default implementation, is mocked in the
+/// same way as the methods of a `#[mock_impl]`. Associated types and constants take
+/// their value from `#[mocked_with(...)]` or from their default in the trait. Associated types
+/// without either are set to `()`.
+pub fn mock_trait(item: &mut ItemTrait, remote: Option<&Path>, naming: &Naming) -> TokenStream {
+    let trait_name = &item.ident;
+    let vis = &item.vis;
+    let mock_name = naming.mock_name(trait_name);
+    let generics = &item.generics;
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let unsafety = &item.unsafety;
+
+    let trait_path: Path = match remote {
+        Some(remote) => parse_quote!(#remote #ty_generics),
+        None => parse_quote!(#trait_name #ty_generics),
+    };
+    let original_type: Type = parse_quote!(#trait_path);
+    let context = match MockContext::new(&original_type, naming) {
+        Ok(context) => context,
+        Err(error) => return TokenStream::from(error.to_compile_error()),
+    };
+
+    let methods: Vec<String> = item
+        .items
+        .iter()
+        .filter_map(|trait_item| match trait_item {
+            TraitItem::Fn(f) => Some(f.sig.ident.to_string()),
+            _ => None,
+        })
+        .collect();
+    let (items, helpers): (Vec<_>, Vec<_>) = item
+        .items
+        .iter_mut()
+        .map(|trait_item| mock_trait_item(&context, generics, &trait_path, trait_item, &methods))
+        .unzip();
+
+    let phantom = generics.params.iter().filter_map(|param| match param {
+        GenericParam::Type(ty) => {
+            let ident = &ty.ident;
+            Some(quote!(#ident))
+        }
+        GenericParam::Lifetime(lt) => {
+            let lifetime = &lt.lifetime;
+            Some(quote!(&#lifetime ()))
+        }
+        GenericParam::Const(_) => None,
+    });
+    let instance = instance_name(&mock_name);
+    let state = generate_mock_state(vis, &mock_name, generics, &Tracking::Owned(parse_quote!(1)));
+
+    TokenStream::from(quote! {
+        #vis struct #mock_name #generics (
+            ::std::marker::PhantomData<fn() -> (#(#phantom,)*)>,
+            #[doc(hidden)]
+            pub #instance,
+        ) #where_clause;
+
+        impl #impl_generics #mock_name #ty_generics #where_clause {
+            pub fn mock_new() -> Self {
+                Self(::std::marker::PhantomData, #instance::new())
+            }
+        }
+
+        impl #impl_generics ::std::default::Default for #mock_name #ty_generics #where_clause {
+            fn default() -> Self {
+                Self::mock_new()
+            }
+        }
+
+        impl #impl_generics ::std::clone::Clone for #mock_name #ty_generics #where_clause {
+            fn clone(&self) -> Self {
+                Self::mock_new()
+            }
+        }
+
+        impl #impl_generics ::std::fmt::Debug for #mock_name #ty_generics #where_clause {
+            fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+                f.write_str(stringify!(#mock_name))
+            }
+        }
+
+        #state
+
+        #unsafety impl #impl_generics #trait_path for #mock_name #ty_generics
+        #where_clause
+        {
+            #(#items)*
+        }
+
+        impl #impl_generics #mock_name #ty_generics #where_clause {
+            #(#helpers)*
+        }
+    })
+}
+
+/// Generates the implementation of a single item of the trait on the mock, along with the helpers
+/// that tests use to configure it.
+fn mock_trait_item(
+    context: &MockContext,
+    generics: &Generics,
+    trait_path: &Path,
+    trait_item: &mut TraitItem,
+    methods: &[String],
+) -> (proc_macro2::TokenStream, proc_macro2::TokenStream) {
+    match trait_item {
+        TraitItem::Fn(f) => {
+            let function = ImplItemFn {
+                attrs: f.attrs.clone(),
+                vis: Visibility::Inherited,
+                modifiers: f.modifiers.clone(),
+                sig: f.sig.clone(),
+                block: Block {
+                    brace_token: Brace::default(),
+                    stmts: vec![],
+                },
+            };
+            take_mocked_with(&mut f.attrs);
+            let (function, helpers) = context.replace_self_from_function_with_mocks(
+                function,
+                generics,
+                Some(trait_path),
+                None,
+                methods,
+            );
+            (quote!(#function), helpers)
+        }
+        TraitItem::Type(ty) => {
+            let ident = &ty.ident;
+            let (type_generics, _, type_where_clause) = ty.generics.split_for_impl();
+            let value = match (take_mocked_with(&mut ty.attrs), &ty.default) {
+                (Some(value), _) => value,
+                (None, Some((_, default))) => quote!(#default),
+                (None, None) => quote!(()),
+            };
+            (
+                quote! {
+                    type #ident #type_generics = #value #type_where_clause;
+                },
+                proc_macro2::TokenStream::new(),
+            )
+        }
+        TraitItem::Const(c) => {
+            let ident = &c.ident;
+            let ty = &c.ty;
+            let value = match (take_mocked_with(&mut c.attrs), &c.default) {
+                (Some(value), _) => value,
+                (None, Some((_, default))) => quote!(#default),
+                (None, None) => syn::Error::new(
+                    c.span(),
+                    "associated constants need a default value or `#[mocked_with(value)]` to be \
+                     mocked",
+                )
+                .to_compile_error(),
+            };
+            (
+                quote! {
+                    const #ident: #ty = #value;
+                },
+                proc_macro2::TokenStream::new(),
+            )
+        }
+        other => (
+            syn::Error::new(
+                other.span(),
+                "only functions, associated types and constants can be mocked in traits",
+            )
+            .to_compile_error(),
+            proc_macro2::TokenStream::new(),
+        ),
+    }
+}
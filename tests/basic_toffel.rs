@@ -1,3 +1,5 @@
+#![allow(dead_code)]
+
 use assert_proc::assert_proc;
 use mocktoffel::{mock, toffel};
 
@@ -0,0 +1,51 @@
+#![allow(dead_code)]
+
+use mocktoffel::{mock, mock_impl, toffel};
+
+#[mock(cfg = "any()")]
+#[derive(Debug, Default, PartialEq)]
+pub struct Never {
+    value: u32,
+}
+
+#[mock_impl(cfg = "any()")]
+impl Never {
+    #[mocked_with(7)]
+    pub fn value(&self) -> u32 {
+        self.value
+    }
+}
+
+#[toffel(cfg = "any()")]
+struct Unmocked {
+    #[mocked]
+    never: Never,
+}
+
+#[mock(cfg = "all()")]
+#[derive(Default)]
+pub struct Always {
+    value: u32,
+}
+
+#[toffel(always)]
+struct Mocked {
+    #[mocked]
+    always: Always,
+}
+
+#[test]
+fn originals_are_kept_outside_the_cfg() {
+    let unmocked = Unmocked {
+        never: Never { value: 3 },
+    };
+    assert_eq!(unmocked.never.value(), 3);
+}
+
+#[test]
+fn mocks_are_generated_under_the_cfg() {
+    let mocked = Mocked {
+        always: AlwaysMock::mock_new(),
+    };
+    assert_eq!(mocked.always.value, 0);
+}
@@ -0,0 +1,35 @@
+#![allow(dead_code)]
+
+use mocktoffel::{mock, toffel};
+
+#[mock]
+#[derive(Default)]
+pub struct Connection {
+    open: bool,
+}
+
+#[mock]
+pub trait Backend {
+    #[mocked_with(ConnectionMock)]
+    type Conn;
+
+    fn connect(&self) -> Option<Self::Conn>;
+}
+
+#[toffel]
+struct Service<B: Backend> {
+    backend: B,
+    #[mocked]
+    conn: <B as Backend>::Conn,
+}
+
+#[test]
+fn associated_types_resolve_through_the_trait_mock() {
+    let service = Service {
+        backend: BackendMock::mock_new(),
+        conn: ConnectionMock::mock_new(),
+    };
+
+    assert!(!service.conn.open);
+    assert!(service.backend.connect().is_none());
+}
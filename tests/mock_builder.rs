@@ -0,0 +1,142 @@
+#![allow(dead_code)]
+
+use mocktoffel::{mock, toffel};
+
+#[mock]
+#[derive(Debug, PartialEq)]
+pub struct Config {
+    #[mocked_with(String::from("localhost"))]
+    host: String,
+    #[mocked_with(8080)]
+    port: u16,
+    verbose: bool,
+}
+
+#[mock]
+#[derive(Debug, PartialEq)]
+pub struct Pair(#[mocked_with(1)] u8, String);
+
+#[mock]
+#[derive(Debug, PartialEq)]
+pub struct Wrapper<T: Default> {
+    inner: T,
+}
+
+#[mock]
+#[derive(Debug, PartialEq)]
+enum Message {
+    Text {
+        #[mocked_with(String::from("hello"))]
+        body: String,
+        urgent: bool,
+    },
+    Binary(u32, #[mocked_with(vec![0])] Vec<u8>),
+    Empty,
+}
+
+#[test]
+fn builds_structs_from_the_mocked_values() {
+    let config = ConfigMock::builder().port(9000).build();
+    assert_eq!(
+        config,
+        ConfigMock {
+            host: String::from("localhost"),
+            port: 9000,
+            verbose: false,
+            ..ConfigMock::mock_new()
+        }
+    );
+
+    let config = ConfigMock::builder()
+        .host(String::from("example.com"))
+        .verbose(true)
+        .build();
+    assert_eq!(config.host, "example.com");
+    assert_eq!(config.port, 8080);
+    assert!(config.verbose);
+}
+
+#[test]
+fn builds_tuple_and_generic_structs() {
+    let pair = PairMock::builder().field_1(String::from("b")).build();
+    assert_eq!(pair, PairMock(1, String::from("b")));
+
+    let wrapper = WrapperMock::<u8>::builder().inner(3).build();
+    assert_eq!(wrapper.inner, 3);
+}
+
+#[test]
+fn changes_mocks_through_a_closure() {
+    let config = ConfigMock::mock_with(|config| config.verbose = true);
+    assert_eq!(config.host, "localhost");
+    assert!(config.verbose);
+
+    let message = MessageMock::mock_with(|message| *message = MessageMock::Empty);
+    assert_eq!(message, MessageMock::Empty);
+}
+
+#[test]
+fn builds_enum_variants_from_the_mocked_values() {
+    assert_eq!(
+        MessageMock::text_builder().urgent(true).build(),
+        MessageMock::Text {
+            body: String::from("hello"),
+            urgent: true,
+        }
+    );
+    assert_eq!(
+        MessageMock::binary_builder().field_0(7).build(),
+        MessageMock::Binary(7, vec![0])
+    );
+    assert_eq!(
+        MessageMock::binary_builder().field_1(vec![1, 2]).build(),
+        MessageMock::Binary(0, vec![1, 2])
+    );
+}
+
+#[mock]
+#[toffel]
+#[derive(Debug)]
+pub struct Service {
+    #[mocked]
+    #[mocked_with(ConfigMock::mock_new())]
+    config: Config,
+    retries: u8,
+}
+
+#[mock]
+#[toffel]
+#[derive(Debug)]
+enum Outcome {
+    HTTPError(u16),
+    Done {
+        #[mocked]
+        #[mocked_with(ConfigMock::mock_new())]
+        config: Config,
+    },
+}
+
+#[test]
+fn sets_mocked_fields_to_their_mock() {
+    let service = ServiceMock::builder().retries(3).build();
+    assert_eq!(service.config, ConfigMock::mock_new());
+    assert_eq!(service.retries, 3);
+
+    let config = ConfigMock::builder().port(1).build();
+    let service = ServiceMock::builder().config(config).build();
+    assert_eq!(service.config.port, 1);
+
+    let service = ServiceMock::mock_with(|service| service.config.port = 2);
+    assert_eq!(service.config.port, 2);
+
+    let outcome = OutcomeMock::done_builder()
+        .config(ConfigMock::builder().verbose(true).build())
+        .build();
+    assert!(matches!(outcome, OutcomeMock::Done { config } if config.verbose));
+}
+
+#[test]
+fn names_the_builders_of_variants_in_snake_case() {
+    let outcome = OutcomeMock::http_error_builder().field_0(404).build();
+    assert!(matches!(outcome, OutcomeMock::HTTPError(404)));
+}
@@ -0,0 +1,78 @@
+#![allow(dead_code)]
+
+use mocktoffel::{mock, mock_impl, toffel};
+use std::borrow::Cow;
+use std::cell::OnceCell;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+#[mock]
+#[derive(Clone, Default)]
+struct Worker {
+    id: u32,
+}
+
+#[mock_impl]
+impl Worker {
+    pub fn run(&self) -> bool {
+        true
+    }
+}
+
+#[toffel]
+struct Pool {
+    #[mocked]
+    workers: [Worker; 2],
+    #[mocked]
+    spare: Vec<Worker>,
+    #[mocked]
+    leader: Option<Rc<Worker>>,
+    #[mocked]
+    by_name: HashMap<String, Worker>,
+}
+
+#[toffel]
+struct Backups {
+    #[mocked]
+    restored: Result<Worker, String>,
+    #[mocked]
+    shared: Cow<'static, Worker>,
+    #[mocked]
+    last: Reverse<Worker>,
+    #[mocked]
+    started: OnceCell<Worker>,
+}
+
+#[test]
+fn containers_keep_their_type() {
+    let pool = Pool {
+        workers: [WorkerMock::mock_new(), WorkerMock::mock_new()],
+        spare: vec![WorkerMock::mock_new()],
+        leader: Some(Rc::new(WorkerMock::mock_new())),
+        by_name: HashMap::from([("main".to_string(), WorkerMock::mock_new())]),
+    };
+
+    assert!(!pool.workers[1].run());
+    assert!(!pool.spare[0].run());
+    let _ = pool.workers[0].expect_run().never();
+    let _ = pool.workers[1].expect_run().times(1);
+    let _ = pool.spare[0].expect_run().times(1);
+    assert!(pool.leader.as_ref().is_some_and(|leader| leader.id == 0));
+    assert_eq!(pool.by_name["main"].id, 0);
+}
+
+#[test]
+fn wrappers_keep_their_type() {
+    let backups = Backups {
+        restored: Ok(WorkerMock::mock_new()),
+        shared: Cow::Owned(WorkerMock::mock_new()),
+        last: Reverse(WorkerMock::mock_new()),
+        started: OnceCell::from(WorkerMock::mock_new()),
+    };
+
+    assert!(backups.restored.as_ref().is_ok_and(|worker| !worker.run()));
+    assert!(!backups.shared.run());
+    assert!(!backups.last.0.run());
+    assert!(backups.started.get().is_some_and(|worker| worker.id == 0));
+}
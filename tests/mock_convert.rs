@@ -0,0 +1,174 @@
+use std::cell::OnceCell;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use mocktoffel::{mock, mock_impl, toffel};
+
+#[mock(convert)]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Config {
+    host: String,
+    ports: Vec<u16>,
+}
+
+#[mock_impl]
+impl Config {
+    pub fn host(&self) -> String {
+        self.host.clone()
+    }
+}
+
+#[mock(convert)]
+#[derive(Debug, PartialEq)]
+pub struct Pair<T: Default>(T, String);
+
+#[mock(convert)]
+#[derive(Debug, PartialEq)]
+enum Message {
+    Text {
+        body: String,
+        urgent: bool,
+    },
+    Binary(Vec<u8>),
+    #[mocked_with]
+    Empty,
+}
+
+#[mock(convert)]
+#[toffel]
+#[derive(Debug)]
+pub struct Service {
+    #[mocked]
+    #[mocked_with(ConfigMock::mock_new())]
+    config: Config,
+    name: String,
+}
+
+// The fields are only replaced in the mock, as `any()` never holds.
+#[mock(convert)]
+#[toffel(cfg = "any()")]
+#[derive(Debug)]
+pub struct Cluster {
+    #[mocked]
+    nodes: Vec<Config>,
+    #[mocked]
+    primary: Option<Config>,
+    #[mocked]
+    #[mocked_with(Err(String::new()))]
+    standby: Result<Config, String>,
+    #[mocked]
+    leader: OnceCell<Config>,
+    #[mocked]
+    shared: Rc<Config>,
+    #[mocked]
+    synced: Arc<Config>,
+    #[mocked]
+    #[mocked_with(Box::pin(ConfigMock::mock_new()))]
+    pinned: Pin<Box<Config>>,
+}
+
+#[test]
+fn converts_structs_to_and_from_their_mock() {
+    let config = Config {
+        host: String::from("localhost"),
+        ports: vec![80, 443],
+    };
+    let mock = ConfigMock::from(config);
+    assert_eq!(mock.host, "localhost");
+    assert_eq!(mock.ports, [80, 443]);
+
+    let config: Config = mock.into();
+    assert_eq!(
+        config,
+        Config {
+            host: String::from("localhost"),
+            ports: vec![80, 443],
+        }
+    );
+}
+
+#[test]
+fn converts_mocks_with_expectations() {
+    let mock = ConfigMock::mock_with(|config| config.host = String::from("example.com"));
+    let _ = mock.host();
+    let _ = mock.expect_host().times(1);
+
+    let config = Config::from(mock);
+    assert_eq!(config.host, "example.com");
+}
+
+#[test]
+fn converts_tuple_and_generic_structs() {
+    let mock = PairMock::from(Pair(3u8, String::from("three")));
+    assert_eq!(mock, PairMock(3, String::from("three")));
+    assert_eq!(Pair::from(mock), Pair(3, String::from("three")));
+}
+
+#[test]
+fn converts_every_variant_of_enums() {
+    let text = Message::Text {
+        body: String::from("hello"),
+        urgent: true,
+    };
+    assert_eq!(
+        MessageMock::from(text),
+        MessageMock::Text {
+            body: String::from("hello"),
+            urgent: true,
+        }
+    );
+    assert_eq!(
+        Message::from(MessageMock::Binary(vec![1, 2])),
+        Message::Binary(vec![1, 2])
+    );
+    assert_eq!(Message::from(MessageMock::mock_new()), Message::Empty);
+}
+
+#[test]
+fn converts_the_mocked_fields() {
+    let service = ServiceMock::mock_with(|service| service.name = String::from("api"));
+    let service = Service::from(service);
+    assert_eq!(service.name, "api");
+    assert_eq!(service.config.host, "");
+
+    let service = ServiceMock::from(service);
+    assert_eq!(service.config, ConfigMock::mock_new());
+}
+
+#[test]
+fn converts_the_items_of_mocked_containers() {
+    let node = |host: &str| Config {
+        host: String::from(host),
+        ports: vec![22],
+    };
+    let cluster = Cluster {
+        nodes: vec![node("a"), node("b")],
+        primary: Some(node("a")),
+        standby: Ok(node("c")),
+        leader: OnceCell::from(node("a")),
+        shared: Rc::new(node("d")),
+        synced: Arc::new(node("e")),
+        pinned: Box::pin(node("f")),
+    };
+
+    let mock = ClusterMock::from(cluster);
+    assert_eq!(mock.nodes[1].host, "b");
+    assert_eq!(mock.primary.as_ref().unwrap().ports, [22]);
+    assert_eq!(mock.standby.as_ref().unwrap().host, "c");
+    assert_eq!(mock.leader.get().unwrap().host, "a");
+    assert_eq!(mock.shared.host, "d");
+    assert_eq!(mock.synced.host, "e");
+    assert_eq!(mock.pinned.host, "f");
+    let _ = mock.nodes[0].host();
+    let _ = mock.nodes[0].expect_host().times(1);
+
+    let cluster = Cluster::from(mock);
+    assert_eq!(cluster.nodes, [node("a"), node("b")]);
+    assert_eq!(cluster.primary, Some(node("a")));
+    assert_eq!(cluster.standby, Ok(node("c")));
+    assert_eq!(cluster.leader.get(), Some(&node("a")));
+    assert_eq!(*cluster.shared, node("d"));
+    assert_eq!(*cluster.synced, node("e"));
+    assert_eq!(*cluster.pinned, node("f"));
+}
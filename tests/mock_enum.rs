@@ -0,0 +1,86 @@
+#![allow(dead_code)]
+
+use mocktoffel::{mock, toffel};
+
+#[mock]
+#[derive(Debug, Default, PartialEq)]
+pub struct User {
+    name: String,
+}
+
+#[mock]
+#[derive(Debug, PartialEq)]
+enum Event {
+    Created {
+        by: String,
+        #[mocked_with(7)]
+        at: u64,
+    },
+    Deleted(u32),
+}
+
+#[mock]
+#[derive(Debug, PartialEq)]
+enum Status {
+    Active(u32),
+    #[mocked_with = Status::Suspended { days: 3 }]
+    Suspended {
+        days: u32,
+    },
+}
+
+#[mock]
+#[derive(Debug, PartialEq)]
+enum Priority {
+    Low,
+    #[mocked_with(Priority::High(2))]
+    High(u8),
+}
+
+#[mock]
+#[derive(Debug, PartialEq)]
+enum Shape {
+    Point,
+    #[mocked_with]
+    Circle {
+        radius: u32,
+    },
+}
+
+#[test]
+fn named_variants_are_built_from_their_fields() {
+    assert_eq!(
+        EventMock::mock_new(),
+        EventMock::Created {
+            by: String::new(),
+            at: 7
+        }
+    );
+    assert_eq!(StatusMock::mock_new(), StatusMock::Suspended { days: 3 });
+    assert_eq!(PriorityMock::mock_new(), PriorityMock::High(2));
+    assert_ne!(PriorityMock::Low, PriorityMock::mock_new());
+    assert_eq!(ShapeMock::mock_new(), ShapeMock::Circle { radius: 0 });
+    assert_ne!(EventMock::Deleted(1), EventMock::mock_new());
+    assert_ne!(StatusMock::Active(1), StatusMock::mock_new());
+    assert_ne!(ShapeMock::Point, ShapeMock::mock_new());
+}
+
+#[toffel]
+enum Audit {
+    Created {
+        #[mocked]
+        by: User,
+        at: u64,
+    },
+    Removed,
+}
+
+#[test]
+fn named_variant_fields_are_mocked() {
+    let audit = Audit::Created {
+        by: UserMock::mock_new(),
+        at: 1,
+    };
+    assert!(matches!(audit, Audit::Created { by, at: 1 } if by.name.is_empty()));
+    assert!(!matches!(Audit::Removed, Audit::Created { .. }));
+}
@@ -0,0 +1,206 @@
+use mocktoffel::{matchers, mock, mock_impl};
+
+matchers!();
+use matchers::{Sequence, arg, gt};
+use std::sync::Arc;
+use std::thread;
+
+#[mock]
+#[derive(Clone, Debug, Default)]
+pub struct Account {
+    balance: i64,
+}
+
+#[mock_impl]
+impl Account {
+    pub fn deposit(&self, _amount: i64) {}
+
+    pub fn withdraw(&self, _amount: i64) {}
+
+    pub fn close(&self) {}
+
+    pub fn finish(self) {}
+
+    pub fn balance(&self) -> i64 {
+        self.balance
+    }
+}
+
+#[mock]
+pub trait Ledger {
+    fn write(&self, entry: &str);
+}
+
+#[test]
+fn checks_cardinalities_right_away() {
+    let account = AccountMock::mock_new();
+    account.deposit(1);
+    account.deposit(2);
+
+    let _ = account.expect_deposit().at_least(2).at_most(3);
+    let _ = account.expect_withdraw().never();
+}
+
+#[test]
+fn verifies_deferred_expectations_on_drop() {
+    let account = AccountMock::mock_new();
+    account.deposit(1);
+    let _ = account.expect_deposit().deferred().times(2);
+    let _ = account.expect_withdraw().deferred().with(arg(gt(10)));
+    let _ = account.expect_close().deferred().never();
+
+    account.deposit(2);
+    account.deposit(3);
+    account.withdraw(5);
+    account.withdraw(20);
+}
+
+#[test]
+fn records_the_calls_made_on_other_threads() {
+    let account = Arc::new(AccountMock::mock_new());
+    let ledger = Arc::new(LedgerMock::mock_new());
+    let _ = account.expect_withdraw().deferred().times(1);
+
+    let shared: Arc<dyn Ledger + Send + Sync> = ledger.clone();
+    let moved = Arc::clone(&account);
+    thread::spawn(move || {
+        moved.deposit(30);
+        moved.withdraw(10);
+        shared.write("withdrawal");
+    })
+    .join()
+    .unwrap();
+
+    let _ = account.expect_deposit().times(1).with(arg(gt(20)));
+    let _ = ledger.expect_write().times(1);
+}
+
+#[test]
+fn returns_the_configured_values_on_other_threads() {
+    let account = Arc::new(AccountMock::mock_new());
+    account.return_const_balance(5);
+    let moved = Arc::clone(&account);
+    assert_eq!(thread::spawn(move || moved.balance()).join().unwrap(), 5);
+
+    let mut calls = 0;
+    account.returning_balance(move |()| {
+        calls += 1;
+        calls
+    });
+    let handles: Vec<_> = (0..4)
+        .map(|_| {
+            let moved = Arc::clone(&account);
+            thread::spawn(move || moved.balance())
+        })
+        .collect();
+    let mut balances: Vec<i64> = handles
+        .into_iter()
+        .map(|handle| handle.join().unwrap())
+        .collect();
+    balances.sort_unstable();
+    assert_eq!(balances, [1, 2, 3, 4]);
+}
+
+#[test]
+#[should_panic(
+    expected = "unsatisfied expectations of `AccountMock`:\n    `deposit` was \
+                expected to be called at least 1 time(s), but it was called 0 time(s)"
+)]
+fn panics_on_drop_with_unsatisfied_expectations() {
+    let account = AccountMock::mock_new();
+    account.deposit(1);
+    let _ = account.expect_deposit().deferred();
+}
+
+#[test]
+#[should_panic(
+    expected = "calls of its methods:\n    deposit: 1 call(s)\n    withdraw: 3 call(s)\n    \
+                close: 0 call(s)"
+)]
+fn reports_every_method_on_checkpoint() {
+    let account = AccountMock::mock_new();
+    let _ = account.expect_withdraw().deferred().at_most(2);
+    account.deposit(1);
+    for amount in 1..=3 {
+        account.withdraw(amount);
+    }
+    account.checkpoint();
+}
+
+#[test]
+fn verifies_only_the_expectations_of_the_dropped_mock() {
+    let account = AccountMock::mock_new();
+    let _ = account.expect_close().deferred().times(1);
+
+    drop(account.clone());
+    AccountMock::mock_new().finish();
+    account.close();
+}
+
+#[test]
+fn forgets_expectations_after_a_checkpoint() {
+    let account = AccountMock::mock_new();
+    let _ = account.expect_deposit().deferred().times(1);
+    account.deposit(1);
+    account.checkpoint();
+
+    account.deposit(2);
+}
+
+#[test]
+#[should_panic(expected = "the test failed on its own")]
+fn does_not_panic_again_when_the_test_fails() {
+    let account = AccountMock::mock_new();
+    let _ = account.expect_deposit().deferred();
+    panic!("the test failed on its own");
+}
+
+#[test]
+fn verifies_sequences_across_mocks() {
+    let sequence = Sequence::new();
+    let account = AccountMock::mock_new();
+    let ledger = LedgerMock::mock_new();
+    let _ = account.expect_deposit().in_sequence(&sequence);
+    let _ = ledger.expect_write().in_sequence(&sequence);
+    let _ = account.expect_close().in_sequence(&sequence);
+
+    account.deposit(1);
+    ledger.write("deposit 1");
+    account.close();
+    ledger.checkpoint();
+}
+
+#[test]
+#[should_panic(
+    expected = "`LedgerMock::write` was called after `AccountMock::close`, which \
+                comes later in its sequence"
+)]
+fn panics_on_calls_out_of_sequence() {
+    let sequence = Sequence::new();
+    let account = AccountMock::mock_new();
+    let ledger = LedgerMock::mock_new();
+    let _ = account.expect_close().in_sequence(&sequence);
+    let _ = ledger.expect_write().in_sequence(&sequence);
+    let _ = account.expect_close().in_sequence(&sequence);
+
+    account.close();
+    account.close();
+    ledger.write("closed");
+    ledger.checkpoint();
+}
+
+#[test]
+fn verifies_methods_repeated_in_a_sequence() {
+    let sequence = Sequence::new();
+    let account = AccountMock::mock_new();
+    let ledger = LedgerMock::mock_new();
+    let _ = account.expect_close().in_sequence(&sequence).times(1);
+    let _ = ledger.expect_write().in_sequence(&sequence);
+    let _ = account.expect_close().in_sequence(&sequence).times(1);
+
+    account.close();
+    ledger.write("closed");
+    account.close();
+    account.checkpoint();
+    ledger.checkpoint();
+}
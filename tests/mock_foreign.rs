@@ -0,0 +1,81 @@
+#![allow(dead_code)]
+
+use http::Transport;
+use mocktoffel::{mock_foreign, toffel};
+
+/// Stands in for a crate that does not use mocktoffel.
+mod http {
+    pub struct Client {
+        base: String,
+    }
+
+    impl Client {
+        pub fn get(&self, path: &str) -> Result<String, u16> {
+            Ok(format!("{}{path}", self.base))
+        }
+
+        pub fn with_timeout(self, _secs: u64) -> Self {
+            self
+        }
+    }
+
+    pub trait Transport {
+        fn send(&mut self, bytes: &[u8]) -> usize;
+
+        fn name(&self) -> String {
+            String::from("tcp")
+        }
+    }
+}
+
+mock_foreign! {
+    impl http::Client {
+        #[mocked_with(Ok(String::new()))]
+        pub fn get(&self, path: &str) -> Result<String, u16>;
+
+        pub fn with_timeout(self, secs: u64) -> http::Client;
+    }
+
+    impl std::fmt::Display for http::Client {
+        #[mocked_with(Ok(()))]
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result;
+    }
+
+    trait http::Transport {
+        fn send(&mut self, bytes: &[u8]) -> usize;
+    }
+}
+
+#[toffel]
+struct Downloader {
+    #[mocked(foreign)]
+    client: http::Client,
+    #[mocked(foreign)]
+    transport: Box<dyn http::Transport>,
+}
+
+#[test]
+fn foreign_types_are_mocked() {
+    let client = ClientMock::mock_new().with_timeout(5);
+    assert_eq!(client.get("/"), Ok(String::new()));
+
+    client.returning_get(|(path,)| Err(path.len() as u16));
+    assert_eq!(client.get("/missing"), Err(8));
+    assert_eq!(client.to_string(), "");
+
+    let expectation = client.expect_get().times(2);
+    assert_eq!(expectation.calls(), ["\"/\"", "\"/missing\""]);
+}
+
+#[test]
+fn foreign_mocks_are_used_in_toffel() {
+    let mut downloader = Downloader {
+        client: ClientMock::mock_new(),
+        transport: Box::new(TransportMock::mock_new()),
+    };
+
+    downloader.transport.send(b"ping");
+    assert_eq!(downloader.transport.name(), "tcp");
+    let _ = downloader.transport.expect_send().times(1);
+    let _ = downloader.client.expect_get().times(0);
+}
@@ -0,0 +1,92 @@
+#![allow(dead_code)]
+#![deny(private_interfaces)]
+
+use mocktoffel::toffel;
+
+#[toffel]
+struct Retry {
+    #[mocked]
+    should_retry: fn(u32) -> bool,
+    #[mocked]
+    on_failure: Box<dyn Fn(&str, u32) -> String>,
+    attempts: u32,
+}
+
+#[test]
+fn function_pointer_fields_are_mocked() {
+    let retry = Retry {
+        should_retry: RetryShouldRetryMock::mock_new(),
+        on_failure: Box::new(RetryOnFailureMock::mock_new()),
+        attempts: 3,
+    };
+
+    assert!(!(retry.should_retry)(1));
+    retry.should_retry.returning_call(|(attempt,)| attempt < 3);
+    assert!((retry.should_retry)(2));
+    assert!(!(retry.should_retry)(retry.attempts));
+
+    let expectation = retry.should_retry.expect_call().times(3);
+    assert_eq!(expectation.calls(), ["1", "2", "3"]);
+}
+
+#[test]
+fn boxed_closure_fields_are_mocked() {
+    let retry = Retry {
+        should_retry: RetryShouldRetryMock::default(),
+        on_failure: Box::new(RetryOnFailureMock::default()),
+        attempts: 0,
+    };
+
+    assert_eq!((retry.on_failure)("timeout", 1), "");
+    retry.on_failure.return_const_call("giving up");
+    assert_eq!((retry.on_failure)("timeout", 2), "giving up");
+
+    let expectation = retry.on_failure.expect_call().times(2);
+    assert_eq!(expectation.calls(), ["\"timeout\", 1", "\"timeout\", 2"]);
+}
+
+#[toffel]
+enum Hook {
+    Before(#[mocked] fn()),
+    After,
+}
+
+#[test]
+fn function_pointers_in_variants_are_mocked() {
+    for hook in [Hook::Before(HookBefore0Mock::mock_new()), Hook::After] {
+        if let Hook::Before(before) = &hook {
+            before();
+            let _ = before.expect_call().times(1);
+        }
+    }
+}
+
+mod jobs {
+    use mocktoffel::toffel;
+
+    struct Ticket(u32);
+
+    /// The mock of `on_done` takes the private `Ticket`, so it is only as visible as the field.
+    #[toffel]
+    pub struct Queue {
+        #[mocked]
+        on_done: fn(Ticket),
+        #[mocked]
+        pub(crate) on_start: fn(u32),
+    }
+
+    #[test]
+    fn mocks_take_the_visibility_of_their_field() {
+        let queue = Queue {
+            on_done: QueueOnDoneMock::mock_new(),
+            on_start: QueueOnStartMock::mock_new(),
+        };
+        (queue.on_done)(Ticket(1));
+        let _ = queue.on_done.expect_call().times(1);
+    }
+}
+
+#[test]
+fn mocks_of_visible_fields_are_visible() {
+    let _ = jobs::QueueOnStartMock::mock_new();
+}
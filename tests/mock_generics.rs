@@ -0,0 +1,81 @@
+#![allow(dead_code)]
+
+use mocktoffel::{mock, mock_impl, toffel};
+
+pub trait Codec {
+    fn id(&self) -> u8;
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Json;
+
+impl Codec for Json {
+    fn id(&self) -> u8 {
+        1
+    }
+}
+
+#[mock]
+struct Buffer<T: Codec, const N: usize>
+where
+    T: Send,
+{
+    codec: Option<T>,
+    #[mocked_with([1; N])]
+    data: [u8; N],
+}
+
+#[mock_impl]
+impl<T: Codec, const N: usize> Buffer<T, N>
+where
+    T: Send,
+{
+    pub fn capacity(&self) -> usize {
+        N
+    }
+
+    pub fn codec(&self) -> Option<&T> {
+        self.codec.as_ref()
+    }
+}
+
+#[mock]
+#[derive(Default)]
+struct Pair<'a, T>(Option<&'a T>, u32)
+where
+    T: Clone;
+
+#[toffel]
+struct Holder<'a, T, const N: usize>
+where
+    T: Codec + Clone + Send,
+{
+    #[mocked]
+    buffers: [Buffer<T, N>; 2],
+    #[mocked]
+    pair: Pair<'a, T>,
+}
+
+#[test]
+fn generic_mocks_keep_bounds_and_const_generics() {
+    let buffer = BufferMock::<Json, 4>::mock_new();
+    assert_eq!(buffer.data, [1; 4]);
+    assert!(buffer.codec.is_none());
+
+    buffer.return_const_capacity(8usize);
+    assert_eq!(buffer.capacity(), 8);
+    assert!(buffer.codec().is_none());
+    let _ = buffer.expect_codec().times(1);
+}
+
+#[test]
+fn generic_fields_are_mocked() {
+    let holder: Holder<'_, Json, 3> = Holder {
+        buffers: [BufferMock::mock_new(), BufferMock::mock_new()],
+        pair: PairMock::mock_new(),
+    };
+
+    assert_eq!(holder.buffers[1].data, [1; 3]);
+    assert!(holder.pair.0.is_none_or(|codec| codec.id() == 1));
+    assert_eq!(holder.pair.1, 0);
+}
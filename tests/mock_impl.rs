@@ -0,0 +1,388 @@
+#![allow(dead_code)]
+
+use std::cmp::Ordering;
+
+use mocktoffel::{mock, mock_impl};
+
+#[mock]
+#[derive(Debug, Default)]
+struct Bar {
+    some: String,
+}
+
+#[mock_impl]
+impl Bar {
+    pub fn bar(&self, count: u32, label: &str) -> usize {
+        label.len() * count as usize
+    }
+
+    pub fn reset(&mut self) {}
+}
+
+#[derive(Default)]
+struct NoDebug;
+
+#[mock]
+#[derive(Default)]
+struct Baz {
+    baz: i32,
+}
+
+#[mock_impl]
+impl Baz {
+    pub fn take(&self, _: NoDebug, (a, b): (i32, i32)) -> i32 {
+        a + b
+    }
+}
+
+#[test]
+fn records_calls_with_arguments() {
+    let mut bar_mock = BarMock::mock_new();
+    bar_mock.bar(2, "two");
+    bar_mock.bar(3, "three");
+    bar_mock.reset();
+
+    let expectation = bar_mock.expect_bar().times(2);
+    assert_eq!(expectation.calls(), ["2, \"two\"", "3, \"three\""]);
+    let _ = bar_mock.expect_reset().times(1);
+}
+
+#[test]
+fn records_arguments_without_debug() {
+    let baz_mock = BazMock::mock_new();
+    baz_mock.take(NoDebug, (1, 2));
+
+    let expectation = baz_mock.expect_take().times(1);
+    assert_eq!(expectation.calls(), ["<mock_impl::NoDebug>, (1, 2)"]);
+}
+
+#[test]
+#[should_panic(
+    expected = "expected `BarMock::bar` to be called 1 time(s), but it was called 2 time(s)\n    1: bar(1, \"a\")\n    2: bar(2, \"b\")"
+)]
+fn lists_actual_calls_on_failure() {
+    let bar_mock = BarMock::mock_new();
+    bar_mock.bar(1, "a");
+    bar_mock.bar(2, "b");
+
+    let _ = bar_mock.expect_bar().times(1);
+}
+
+pub struct Token(u64);
+
+#[mock]
+#[derive(Default)]
+struct Session {
+    user: String,
+}
+
+#[mock_impl]
+impl Session {
+    pub fn name(&self, prefix: &str) -> String {
+        format!("{prefix}{}", self.user)
+    }
+
+    #[mocked_with(Token(7))]
+    pub fn token(&self) -> Token {
+        Token(0)
+    }
+
+    pub fn renew(&self) -> Session {
+        Session::default()
+    }
+}
+
+#[test]
+fn returns_default_when_nothing_is_configured() {
+    let session = SessionMock::mock_new();
+    assert_eq!(session.name("mr "), "");
+    assert_eq!(session.token().0, 7);
+}
+
+#[test]
+fn returns_configured_values() {
+    let session = SessionMock::mock_new();
+    session.returning_name(|(prefix,)| format!("{prefix}toffel"));
+    assert_eq!(session.name("mr "), "mr toffel");
+
+    session.returning_token(|()| Token(42));
+    assert_eq!(session.token().0, 42);
+
+    session.returning_renew(|()| SessionMock {
+        user: "renewed".to_string(),
+        ..SessionMock::mock_new()
+    });
+    assert_eq!(session.renew().user, "renewed");
+
+    session.return_const_name("fixed");
+    assert_eq!(session.name("mr "), "fixed");
+    let _ = session.expect_name().times(2);
+}
+
+#[test]
+fn configures_the_values_of_each_mock() {
+    let session = SessionMock::mock_new();
+    let other = SessionMock::mock_new();
+    session.return_const_name("configured");
+
+    assert_eq!(session.name("mr "), "configured");
+    assert_eq!(other.name("mr "), "");
+}
+
+macro_rules! getter {
+    ($name:ident: $ty:ty) => {
+        pub fn $name(&self) -> $ty {
+            self.$name
+        }
+    };
+}
+
+trait Bounded {
+    const LIMIT: u32;
+}
+
+#[mock]
+#[derive(Default)]
+struct Limits {
+    max: usize,
+}
+
+#[mock_impl]
+impl Limits {
+    const MAX: usize = 16;
+
+    #[mocked_with(2)]
+    const MIN: usize = 1;
+
+    getter!(max: usize);
+
+    pub fn clamp(&self, value: usize) -> usize {
+        value.clamp(Self::MIN, Self::MAX)
+    }
+}
+
+#[mock_impl]
+impl Bounded for Limits {
+    #[mocked_with(99)]
+    const LIMIT: u32 = 10;
+}
+
+#[test]
+fn carries_constants_and_macros_over() {
+    assert_eq!(LimitsMock::MAX, 16);
+    assert_eq!(LimitsMock::MIN, 2);
+    assert_eq!(Limits::MIN, 1);
+    assert_eq!(<LimitsMock as Bounded>::LIMIT, 99);
+    assert_eq!(<Limits as Bounded>::LIMIT, 10);
+
+    let limits = LimitsMock::mock_new();
+    assert_eq!(limits.max(), 0);
+    assert_eq!(limits.clamp(8), 0);
+    let _ = limits.expect_clamp().times(1);
+}
+
+trait Graph {
+    type Node;
+
+    fn root(&self) -> Self::Node;
+}
+
+#[mock]
+#[derive(Debug, Default, PartialEq)]
+struct Node {
+    id: u32,
+}
+
+#[mock_impl]
+impl Node {
+    pub fn root(&self) -> u32 {
+        self.id
+    }
+
+    pub fn link(&self, other: &Node) -> bool {
+        self.id == other.id
+    }
+
+    pub fn first(&self, rest: Vec<Node>) -> Option<Node> {
+        rest.into_iter().next()
+    }
+
+    #[mocked_with(Ok(NodeMock { id: 3, ..NodeMock::default() }))]
+    pub fn split(self) -> Result<Node, String> {
+        Ok(self)
+    }
+
+    pub fn sorted<I>(items: I) -> Vec<Node>
+    where
+        I: IntoIterator<Item = Node>,
+    {
+        items.into_iter().collect()
+    }
+}
+
+#[mock_impl]
+impl Graph for Node {
+    type Node = Option<Node>;
+
+    fn root(&self) -> Option<Node> {
+        None
+    }
+}
+
+#[mock_impl]
+impl PartialOrd<Node> for Node {
+    fn partial_cmp(&self, other: &Node) -> Option<Ordering> {
+        self.id.partial_cmp(&other.id)
+    }
+}
+
+#[mock_impl]
+impl From<u8> for Node {
+    fn from(id: u8) -> Self {
+        Self { id: id.into() }
+    }
+}
+
+#[mock_impl]
+impl From<u16> for Node {
+    fn from(id: u16) -> Self {
+        Self { id: id.into() }
+    }
+}
+
+#[test]
+fn replaces_the_original_type_inside_signatures() {
+    let node = NodeMock::mock_new();
+    assert!(!node.link(&NodeMock::default()));
+
+    node.returning_first(|(rest,)| rest.into_iter().last());
+    assert_eq!(
+        node.first(vec![
+            NodeMock {
+                id: 1,
+                ..NodeMock::default()
+            },
+            NodeMock {
+                id: 2,
+                ..NodeMock::default()
+            }
+        ]),
+        Some(NodeMock {
+            id: 2,
+            ..NodeMock::default()
+        })
+    );
+
+    let sorted: Vec<NodeMock> = NodeMock::sorted(vec![NodeMock::default()]);
+    assert!(sorted.is_empty());
+
+    let root: <NodeMock as Graph>::Node = Graph::root(&node);
+    assert_eq!(root, None);
+    assert_eq!(node.root(), 0);
+    let _ = node.expect_root().times(1);
+    let _ = node.expect_graph_root().times(1);
+    assert_eq!(node.partial_cmp(&NodeMock::default()), None);
+    let _ = node.expect_partial_ord_node_partial_cmp().times(1);
+    assert_eq!(
+        node.split(),
+        Ok(NodeMock {
+            id: 3,
+            ..NodeMock::default()
+        })
+    );
+}
+
+/// # Safety
+///
+/// Implementors must be able to run from any thread.
+unsafe trait Job {
+    fn run(&self) -> u32;
+}
+
+#[mock]
+#[derive(Default)]
+struct Worker {
+    jobs: u32,
+}
+
+#[mock_impl]
+#[allow(clippy::unused_async)]
+impl Worker {
+    /// Fetches the job with the given id.
+    #[must_use]
+    #[inline]
+    pub async fn fetch(&self, id: u32) -> u32 {
+        id
+    }
+
+    pub const fn jobs(&self) -> u32 {
+        self.jobs
+    }
+
+    #[mocked_with(9)]
+    pub const fn limit() -> u32 {
+        1
+    }
+
+    /// # Safety
+    ///
+    /// The pointer must not outlive the worker.
+    #[mocked_with(std::ptr::null())]
+    pub unsafe fn raw(&self) -> *const u32 {
+        &self.jobs
+    }
+
+    pub extern "C" fn callback(value: u32) -> u32 {
+        value
+    }
+}
+
+#[mock_impl]
+unsafe impl Job for Worker {
+    fn run(&self) -> u32 {
+        self.jobs
+    }
+}
+
+#[test]
+fn keeps_the_signatures_of_methods() {
+    use std::future::Future;
+    use std::task::{Context, Poll, Waker};
+
+    const LIMIT: u32 = Worker::limit();
+    assert_eq!(LIMIT, 1);
+    assert_eq!(WorkerMock::limit(), 9);
+
+    let worker = WorkerMock::mock_new();
+    assert_eq!(worker.jobs(), 0);
+    let _ = worker.expect_jobs().times(1);
+    let _ = worker.expect_limit().times(1);
+
+    worker.return_const_fetch(5_u32);
+    let fetch = std::pin::pin!(worker.fetch(1));
+    assert_eq!(
+        fetch.poll(&mut Context::from_waker(Waker::noop())),
+        Poll::Ready(5)
+    );
+
+    assert!(unsafe { worker.raw() }.is_null());
+    assert_eq!(WorkerMock::callback(3), 0);
+    assert_eq!(worker.run(), 0);
+    let _ = worker.expect_fetch().times(1);
+    let _ = worker.expect_job_run().times(1);
+}
+
+#[test]
+fn keeps_the_implementations_of_a_generic_trait_apart() {
+    let node = NodeMock::mock_new();
+    node.returning_from_u8_from(|(id,)| NodeMock {
+        id: id.into(),
+        ..NodeMock::default()
+    });
+
+    assert_eq!(NodeMock::from(7u8).id, 7);
+    assert_eq!(NodeMock::from(7u16).id, 0);
+    assert_eq!(NodeMock::from(8u16).id, 0);
+    let _ = node.expect_from_u8_from().times(1);
+    let _ = node.expect_from_u16_from().times(2);
+}
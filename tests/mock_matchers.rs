@@ -0,0 +1,148 @@
+#![allow(dead_code)]
+
+use std::borrow::Cow;
+
+use mocktoffel::{matchers, mock, mock_impl};
+
+matchers!();
+use matchers::{and, any, arg, args, contains, eq, gt, le, ne, not, or, predicate, starts_with};
+
+#[mock]
+pub struct Mailer {
+    sent: usize,
+}
+
+pub struct Attachment;
+
+#[mock_impl]
+impl Mailer {
+    pub fn send(&self, _to: &str, _subject: String, _priority: u8) {}
+
+    pub fn send_bytes(&self, _bytes: &[u8]) {}
+
+    pub fn attach(&self, _attachment: Attachment, _name: &str) {}
+
+    pub fn retry(&self, _attempts: u32) {}
+
+    pub fn label(&self, _name: Option<&str>, _tags: &[&str]) {}
+
+    pub fn quote(&self, _text: Cow<'_, str>) {}
+
+    pub fn write(&self, _line: Cow<str>) {}
+}
+
+#[test]
+fn matches_arguments_by_position() {
+    let mailer = MailerMock::mock_new();
+    mailer.send("ann@example.com", String::from("Hello"), 1);
+    mailer.send("bob@example.org", String::from("Re: Hello"), 5);
+
+    let _ = mailer
+        .expect_send()
+        .with(args((eq("ann@example.com"), any(), any())))
+        .times(1);
+    let _ = mailer
+        .expect_send()
+        .with(args((ne("ann@example.com"), starts_with("Re:"), gt(3))))
+        .times(1);
+    let _ = mailer
+        .expect_send()
+        .with(args((contains("@example"), contains("Hello"), le(5))))
+        .times(2);
+}
+
+#[test]
+fn combines_matchers() {
+    let mailer = MailerMock::mock_new();
+    for attempts in [1, 4, 9] {
+        mailer.retry(attempts);
+    }
+
+    let _ = mailer.expect_retry().with(arg(and(gt(1), le(4)))).times(1);
+    let _ = mailer.expect_retry().with(arg(or(eq(1), eq(9)))).times(2);
+    let _ = mailer.expect_retry().with(arg(not(eq(4)))).times(2);
+    let _ = mailer
+        .expect_retry()
+        .with(arg(predicate(|attempts: &u32| attempts % 2 == 1)))
+        .times(2);
+}
+
+#[test]
+fn matches_slices_as_vectors() {
+    let mailer = MailerMock::mock_new();
+    mailer.send_bytes(b"HELO there");
+
+    let _ = mailer
+        .expect_send_bytes()
+        .with(arg(starts_with(b"HELO")))
+        .times(1);
+    let _ = mailer
+        .expect_send_bytes()
+        .with(arg(contains(b"there")))
+        .times(1);
+    let _ = mailer.expect_send_bytes().with(arg(eq(b"HELO"))).times(0);
+}
+
+#[test]
+fn only_any_matches_arguments_that_cannot_be_cloned() {
+    let mailer = MailerMock::mock_new();
+    mailer.attach(Attachment, "report.pdf");
+
+    let _ = mailer
+        .expect_attach()
+        .with(args((any(), eq("report.pdf"))))
+        .times(1);
+    let _ = mailer
+        .expect_attach()
+        .with(args((not(any()), eq("report.pdf"))))
+        .times(0);
+}
+
+#[test]
+fn only_any_matches_arguments_that_may_borrow() {
+    let mailer = MailerMock::mock_new();
+    let name = String::from("inbox");
+    mailer.label(Some(&name), &["urgent"]);
+    mailer.quote(Cow::Borrowed("text"));
+    mailer.write(Cow::Borrowed(&name));
+
+    let _ = mailer.expect_label().with(args((any(), any()))).times(1);
+    let _ = mailer
+        .expect_label()
+        .with(args((not(any()), any())))
+        .times(0);
+    let _ = mailer.expect_quote().times(1);
+    let _ = mailer.expect_write().with(arg(any())).times(1);
+    let _ = mailer.expect_write().with(arg(not(any()))).times(0);
+}
+
+#[test]
+#[should_panic(expected = "argument 3 was `1`, expected > 3")]
+fn reports_the_argument_that_did_not_match() {
+    let mailer = MailerMock::mock_new();
+    mailer.send("ann@example.com", String::from("Hello"), 1);
+
+    let _ = mailer
+        .expect_send()
+        .with(args((any(), eq("Hello"), gt(3))))
+        .times(1);
+}
+
+mod glob {
+    use super::matchers::*;
+
+    // The matchers do not shadow the traits of the prelude when imported with a glob.
+    pub fn distinct<T: Eq + Ord>(mut values: Vec<T>) -> Vec<T> {
+        values.sort();
+        values.dedup();
+        values
+    }
+
+    #[test]
+    fn glob_imports_keep_the_prelude() {
+        let mailer = super::MailerMock::mock_new();
+        mailer.retry(2);
+        let _ = mailer.expect_retry().with(arg(eq(2))).times(1);
+        assert_eq!(distinct(vec![2, 1, 2]), [1, 2]);
+    }
+}
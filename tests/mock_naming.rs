@@ -0,0 +1,66 @@
+#![allow(dead_code)]
+
+use mocktoffel::{mock, mock_impl, toffel};
+
+#[mock(name = "FakeClock")]
+#[derive(Debug, Default)]
+pub struct Clock {
+    now: u64,
+}
+
+#[mock_impl(name = "FakeClock")]
+impl Clock {
+    #[mocked_with(42)]
+    pub fn now(&self) -> u64 {
+        self.now
+    }
+}
+
+/// A production type that already has the name a mock of `Clock` would get by default.
+pub struct ClockMock;
+
+#[mock(prefix = "Mock")]
+pub trait Mailer {
+    fn send(&self, to: &str) -> bool;
+}
+
+#[mock(prefix = "Stub", suffix = "Impl")]
+#[derive(Debug, Default)]
+pub struct Store {
+    items: Vec<u32>,
+}
+
+#[toffel(prefix = "Mock")]
+struct Service {
+    #[mocked(with = FakeClock)]
+    clock: Clock,
+    #[mocked]
+    mailer: Box<dyn Mailer>,
+    #[mocked]
+    on_send: fn(&str),
+}
+
+#[toffel(prefix = "Stub", suffix = "Impl")]
+struct Inventory {
+    #[mocked]
+    store: Store,
+}
+
+#[test]
+fn mocks_are_named_with_the_options() {
+    let service = Service {
+        clock: FakeClock::mock_new(),
+        mailer: Box::new(MockMailer::mock_new()),
+        on_send: MockServiceOnSend::mock_new(),
+    };
+    let _ = ClockMock;
+
+    assert_eq!(service.clock.now(), 42);
+    service.mailer.send("someone");
+    (service.on_send)("someone");
+
+    let inventory = Inventory {
+        store: StubStoreImpl::mock_new(),
+    };
+    assert!(inventory.store.items.is_empty());
+}
@@ -0,0 +1,64 @@
+#![allow(dead_code)]
+
+use mocktoffel::{mock, mock_impl, toffel};
+
+#[mock]
+#[derive(Default)]
+struct Db {
+    url: String,
+}
+
+#[mock_impl]
+impl Db {
+    pub fn query(&self, sql: &str) -> usize {
+        sql.len()
+    }
+
+    pub fn close(&mut self) {}
+}
+
+#[mock]
+pub trait Clock {
+    fn now(&self) -> u64;
+}
+
+#[mock]
+struct Handle {
+    fd: i32,
+}
+
+#[toffel]
+struct View<'a> {
+    #[mocked]
+    db: &'a Db,
+    #[mocked]
+    writer: &'a mut Db,
+    #[mocked]
+    clock: &'a dyn Clock,
+    #[mocked]
+    handle: *const Handle,
+}
+
+#[test]
+fn reference_fields_are_mocked() {
+    let db = DbMock::mock_new();
+    let mut writer = DbMock::mock_new();
+    let clock = ClockMock::mock_new();
+    let handle = HandleMock::mock_new();
+    let view = View {
+        db: &db,
+        writer: &mut writer,
+        clock: &clock,
+        handle: &raw const handle,
+    };
+
+    view.db.return_const_query(3usize);
+    assert_eq!(view.db.query("select"), 3);
+    view.writer.close();
+    assert_eq!(view.clock.now(), 0);
+    // SAFETY: the pointer was created from a live local above.
+    assert_eq!(unsafe { (*view.handle).fd }, 0);
+
+    let _ = writer.expect_close().times(1);
+    let _ = db.expect_query().times(1);
+}
@@ -0,0 +1,125 @@
+#![allow(dead_code)]
+#![deny(unused_braces)]
+
+use mocktoffel::{mock, mock_impl};
+
+#[mock]
+#[derive(Debug, Default)]
+pub struct Account {
+    balance: i64,
+    owner: String,
+}
+
+#[mock_impl(spy)]
+impl Account {
+    pub fn new(owner: &str) -> Account {
+        Account {
+            balance: 0,
+            owner: owner.to_string(),
+        }
+    }
+
+    pub fn deposit(&mut self, amount: i64) -> i64 {
+        self.balance += amount;
+        self.balance
+    }
+
+    pub fn apply(&mut self, (credit, debit): (i64, i64)) -> i64 {
+        self.deposit(credit - debit)
+    }
+
+    pub fn fetch_rate(&self) -> f64 {
+        panic!("the rate is fetched over the network")
+    }
+
+    #[mocked_with(String::from("mocked"))]
+    pub fn describe(&self) -> String {
+        format!("{}: {}", self.owner, self.balance)
+    }
+}
+
+#[mock]
+pub union Bits {
+    int: u32,
+    float: f32,
+}
+
+#[mock_impl(spy)]
+impl Bits {
+    pub fn zero() -> Self {
+        Self { int: 0 }
+    }
+
+    pub fn from_float(float: f32) -> Bits {
+        Bits { float }
+    }
+}
+
+mod ledger {
+    use mocktoffel::mock;
+
+    #[mock]
+    #[derive(Debug, Default)]
+    pub struct Entry {
+        pub amount: i64,
+        pub memo: String,
+    }
+
+    #[derive(Debug, Default)]
+    pub struct Tag {
+        pub name: String,
+    }
+}
+
+use ledger::{EntryMock, EntryMockExpectation};
+
+#[mock_impl(spy)]
+impl ledger::Entry {
+    pub fn blank(memo: &str) -> ledger::Entry {
+        ledger::Entry {
+            amount: 0,
+            memo: memo.to_string(),
+        }
+    }
+
+    pub fn tag(&self) -> ledger::Tag {
+        ledger::Tag {
+            name: self.memo.clone(),
+        }
+    }
+}
+
+#[test]
+fn spies_run_the_original_bodies() {
+    let mut account = AccountMock::new("ann");
+    assert_eq!(account.owner, "ann");
+
+    assert_eq!(account.deposit(5), 5);
+    assert_eq!(account.apply((4, 1)), 8);
+    assert_eq!(account.describe(), "mocked");
+
+    let _ = account.expect_deposit().times(2);
+    let _ = account.expect_apply().times(1);
+}
+
+#[test]
+fn spies_use_the_configured_values() {
+    let mut account = AccountMock::mock_new();
+    account.returning_fetch_rate(|()| 1.5);
+    assert!((account.fetch_rate() - 1.5).abs() < f64::EPSILON);
+
+    account.return_const_deposit(-1_i64);
+    assert_eq!(account.deposit(2), -1);
+    assert_eq!(account.balance, 0);
+}
+
+#[test]
+fn spies_build_unions_and_mocks_from_other_modules() {
+    assert_eq!(unsafe { BitsMock::zero().int }, 0);
+    assert!((unsafe { BitsMock::from_float(1.5).float } - 1.5).abs() < f32::EPSILON);
+
+    let entry = EntryMock::blank("rent");
+    assert_eq!(entry.memo, "rent");
+    assert_eq!(entry.tag().name, "rent");
+    let _ = entry.expect_tag().times(1);
+}
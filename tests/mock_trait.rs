@@ -0,0 +1,97 @@
+use mocktoffel::{mock, toffel};
+use std::sync::Arc;
+
+#[mock]
+#[allow(clippy::len_without_is_empty)]
+pub trait Storage {
+    #[mocked_with(String)]
+    type Key;
+
+    #[mocked_with(16)]
+    const CAPACITY: usize;
+
+    fn get(&self, key: &str) -> Option<Vec<u8>>;
+
+    fn put(&mut self, key: &str, value: Vec<u8>);
+
+    fn len(&self) -> usize {
+        0
+    }
+
+    fn contains<Q: AsRef<str>>(&self, key: Q) -> bool;
+}
+
+#[mock]
+trait Codec<T> {
+    fn encode(&self, value: T) -> Vec<u8>;
+
+    fn decode(&self, bytes: &[u8]) -> Option<T>;
+}
+
+fn store(storage: &mut impl Storage, key: &str) -> Option<Vec<u8>> {
+    storage.put(key, vec![1, 2, 3]);
+    storage.get(key)
+}
+
+#[test]
+fn mock_implements_trait() {
+    let mut storage = StorageMock::mock_new();
+    assert_eq!(store(&mut storage, "key"), None);
+    assert_eq!(StorageMock::CAPACITY, 16);
+    let _: <StorageMock as Storage>::Key = String::new();
+
+    let _ = storage.expect_put().times(1);
+    assert_eq!(storage.expect_get().times(1).calls(), ["\"key\""]);
+}
+
+#[test]
+fn default_and_generic_methods_are_mocked() {
+    let storage = StorageMock::mock_new();
+    storage.return_const_len(3usize);
+    assert_eq!(storage.len(), 3);
+    assert!(!storage.contains("key"));
+
+    let _ = storage.expect_contains().times(1);
+}
+
+#[test]
+fn generic_trait_mock() {
+    let codec = CodecMock::<u32>::mock_new();
+
+    assert_eq!(codec.encode(4), Vec::<u8>::new());
+    assert_eq!(codec.decode(&[9]), None);
+    assert_eq!(codec.expect_encode().times(1).calls(), ["<u32>"]);
+}
+
+#[mock]
+pub trait Clock {
+    fn now(&self) -> u64;
+}
+
+#[mock]
+pub trait Repo {
+    type Entity;
+
+    fn find(&self, id: u32) -> Option<Self::Entity>;
+}
+
+#[toffel]
+struct Scheduler {
+    #[mocked]
+    clock: Box<dyn Clock>,
+    #[mocked]
+    repo: Arc<dyn Repo<Entity = ()> + Send + Sync>,
+}
+
+#[test]
+fn trait_object_fields_are_mocked() {
+    let scheduler = Scheduler {
+        clock: Box::new(ClockMock::mock_new()),
+        repo: Arc::new(RepoMock::mock_new()),
+    };
+    scheduler.clock.return_const_now(5u64);
+
+    assert_eq!(scheduler.clock.now(), 5);
+    assert_eq!(scheduler.repo.find(1), None);
+    let _ = scheduler.repo.expect_find().times(1);
+}
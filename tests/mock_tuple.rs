@@ -0,0 +1,43 @@
+#![allow(dead_code)]
+
+use mocktoffel::{mock, toffel};
+
+#[mock]
+#[derive(Default)]
+struct Db {
+    url: String,
+}
+
+#[mock]
+#[derive(Default)]
+struct Cache {
+    size: usize,
+}
+
+#[toffel]
+struct Config {
+    #[mocked]
+    backends: (Db, Cache),
+    #[mocked(1)]
+    cache: (String, Cache),
+    #[mocked(0)]
+    hooks: (fn(u32), Db),
+}
+
+#[test]
+fn tuple_elements_are_mocked() {
+    let config = Config {
+        backends: (DbMock::mock_new(), CacheMock::mock_new()),
+        cache: (String::from("local"), CacheMock::mock_new()),
+        hooks: (ConfigHooks0Mock::mock_new(), Db::default()),
+    };
+
+    assert_eq!(config.backends.0.url, "");
+    assert_eq!(config.backends.1.size, 0);
+    assert_eq!(config.cache.0, "local");
+    assert_eq!(config.cache.1.size, 0);
+
+    (config.hooks.0)(3);
+    assert_eq!(config.hooks.0.expect_call().times(1).calls(), ["3"]);
+    assert_eq!(config.hooks.1.url, "");
+}
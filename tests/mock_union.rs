@@ -0,0 +1,61 @@
+#![allow(dead_code)]
+
+use mocktoffel::{mock, mock_impl, toffel};
+use std::mem::ManuallyDrop;
+
+#[mock]
+struct SystemClock;
+
+#[mock_impl]
+impl SystemClock {
+    pub fn now(&self) -> u64 {
+        0
+    }
+}
+
+#[mock]
+#[derive(Clone, Copy)]
+union Handle {
+    fd: i32,
+    #[mocked_with(core::ptr::null_mut())]
+    ptr: *mut u8,
+}
+
+#[mock]
+union Value {
+    int: u64,
+    float: f64,
+}
+
+#[toffel]
+union Slot {
+    #[mocked]
+    handle: Handle,
+    raw: ManuallyDrop<String>,
+}
+
+#[test]
+fn unit_structs_are_mocked() {
+    let clock = SystemClockMock::mock_new();
+    clock.return_const_now(42u64);
+
+    assert_eq!(clock.now(), 42);
+    let _ = clock.expect_now().times(1);
+}
+
+#[test]
+fn unions_initialise_the_selected_field() {
+    let handle = HandleMock::mock_new();
+    // SAFETY: `mock_new` initialises `ptr`.
+    assert!(unsafe { handle.ptr }.is_null());
+
+    let value = ValueMock::mock_new();
+    // SAFETY: `mock_new` initialises the first field when none is selected.
+    assert_eq!(unsafe { value.int }, 0);
+
+    let slot = Slot {
+        handle: HandleMock::mock_new(),
+    };
+    // SAFETY: `handle` was initialised above.
+    assert!(unsafe { slot.handle.ptr }.is_null());
+}
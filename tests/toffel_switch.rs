@@ -0,0 +1,36 @@
+#![allow(dead_code)]
+
+mod app {
+    use mocktoffel::{mock, toffel};
+
+    #[mock]
+    #[derive(Debug, Default)]
+    pub struct Db {
+        pub url: String,
+    }
+
+    /// A service whose database is replaced with a mock in tests.
+    #[toffel]
+    #[derive(Debug, Default)]
+    pub struct Service {
+        /// The database the service reads from.
+        #[mocked]
+        pub db: Db,
+        pub name: String,
+    }
+
+    impl Service {
+        pub fn url(&self) -> &str {
+            &self.db.url
+        }
+    }
+}
+
+#[test]
+fn mocked_struct_keeps_its_name_and_visibility() {
+    let service = app::Service::default();
+    let _: &app::DbMock = &service.db;
+
+    assert_eq!(service.url(), "");
+    assert_eq!(service.name, "");
+}
@@ -0,0 +1,33 @@
+use mocktoffel::{mock, mock_impl};
+
+#[mock(always)]
+#[derive(Default)]
+pub struct Meter(u32);
+
+#[mock_impl(always)]
+impl Meter {
+    pub fn read(&self) -> u32 {
+        self.0
+    }
+}
+
+#[mock(always)]
+#[derive(Clone, Copy, Default)]
+pub struct Gauge {
+    level: u8,
+}
+
+#[mock_impl(always)]
+impl Gauge {
+    pub fn level(&self) -> u8 {
+        self.level
+    }
+}
+
+fn main() {
+    let meter = MeterMock::mock_new();
+    let _ = meter.expect_read().deferred().times(1);
+
+    let gauge = GaugeMock::mock_new();
+    let _ = gauge.expect_level().deferred();
+}
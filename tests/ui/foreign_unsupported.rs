@@ -0,0 +1,23 @@
+use mocktoffel::mock_foreign;
+
+mock_foreign! {
+    impl std::vec::Vec<u8> {
+        pub fn len(&self) -> usize;
+    }
+
+    impl<'a> &'a str {
+        pub fn len(&self) -> usize;
+    }
+}
+
+mock_foreign! {
+    #![mock(name = "FakeClient")]
+
+    impl Client {}
+}
+
+mock_foreign! {
+    struct Client;
+}
+
+fn main() {}
@@ -0,0 +1,51 @@
+use std::rc::Weak;
+
+use mocktoffel::{mock, mock_impl, toffel};
+
+#[mock]
+struct Buffer {
+    #[mocked_with = 1]
+    len: usize,
+}
+
+#[mock]
+fn not_a_type() {}
+
+#[mock_impl]
+impl [u8] {}
+
+#[mock(sometimes)]
+struct Sometimes;
+
+#[mock(spy)]
+struct Spied;
+
+#[mock(convert)]
+union Bits {
+    int: u32,
+    float: f32,
+}
+
+#[mock(convert)]
+trait Converted {}
+
+#[mock_impl(convert)]
+impl Spied {}
+
+#[mock(prefix = "", always)]
+struct Unnamed;
+
+#[mock(suffix = "Fake-", always)]
+struct Dashed;
+
+#[mock(always)]
+struct Peer;
+
+#[mock(convert, always)]
+#[toffel(always)]
+struct Link {
+    #[mocked]
+    peer: Weak<Peer>,
+}
+
+fn main() {}
@@ -0,0 +1,26 @@
+use mocktoffel::toffel;
+
+#[toffel]
+struct Fields {
+    #[mocked]
+    never: !,
+    #[mocked]
+    generated: stringify!(Foo),
+    #[mocked(4)]
+    pair: (u8, u8),
+    #[mocked(using = u8)]
+    other: u8,
+    #[mocked]
+    io: Box<dyn std::io::Read + std::io::Write>,
+}
+
+#[toffel(name = "FakeNamed")]
+struct Named {
+    #[mocked]
+    other: u8,
+}
+
+#[toffel]
+fn not_a_type() {}
+
+fn main() {}